@@ -8,8 +8,12 @@ use std::{
     thread,
 };
 
-use indoc::formatdoc;
-use touche::{body::HttpBody, Body, Method, Request, Response, Server, StatusCode};
+use touche::{
+    body::HttpBody,
+    header::CONNECTION,
+    sse::{self, Event as SseEvent},
+    Body, Method, Request, Response, Server, StatusCode,
+};
 
 #[derive(Debug)]
 enum Event {
@@ -66,33 +70,28 @@ fn main() -> std::io::Result<()> {
                 tx.send(Event::User(user_id)).unwrap();
                 users.lock().unwrap().insert(user_id, tx);
 
-                let (sender, body) = Body::channel();
+                let (sender, mut res) = sse::channel();
+                res.headers_mut()
+                    .insert(CONNECTION, "close".parse().unwrap());
+
                 thread::spawn(move || {
                     for event in rx {
-                        let message = match event {
-                            Event::User(id) => formatdoc! {r#"
-                                event: user
-                                data: {{"id": "{id}"}}
-
-                            "#},
-                            Event::Message(user_id, text) => formatdoc! {r#"
-                                event: message
-                                data: {{"userId": {user_id}, "message": "{text}"}}
-
-                            "#},
+                        let event = match event {
+                            Event::User(id) => SseEvent::default()
+                                .event("user")
+                                .data(format!(r#"{{"id": "{id}"}}"#)),
+                            Event::Message(user_id, text) => SseEvent::default()
+                                .event("message")
+                                .data(format!(r#"{{"userId": {user_id}, "message": "{text}"}}"#)),
                         };
 
-                        if let Err(_err) = sender.send(message) {
+                        if let Err(_err) = sender.send(event) {
                             break;
                         }
                     }
                 });
 
-                Response::builder()
-                    .status(StatusCode::OK)
-                    .header("content-type", "text/event-stream")
-                    .header("connection", "close")
-                    .body(body)
+                Ok(res)
             }
 
             "/" => Response::builder()
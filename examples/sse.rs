@@ -1,50 +1,51 @@
-use std::{error::Error, thread, time::Duration};
+use std::{thread, time::Duration};
 
-use indoc::indoc;
-use touche::{header::ACCEPT, Body, Request, Response, Server, StatusCode};
+use touche::{
+    header::ACCEPT,
+    sse::{self, Event},
+    Body, Request, Response, Server, StatusCode,
+};
 
 fn main() -> std::io::Result<()> {
     Server::bind("0.0.0.0:4444").serve(|req: Request<_>| {
         match req.headers().get(ACCEPT).and_then(|a| a.to_str().ok()) {
             Some(accept) if accept.contains("text/event-stream") => {
-                let (sender, body) = Body::channel();
+                let (sender, res) = sse::channel();
+                let sender = sender.keep_alive(Duration::from_secs(15));
 
                 thread::spawn(move || {
-                    sender.send(indoc! {r#"
-                        event: userconnect
-                        data: {"name": "sasha"}
-
-                    "#})?;
+                    sender.send(
+                        Event::default()
+                            .event("userconnect")
+                            .data(r#"{"name": "sasha"}"#),
+                    )?;
 
                     for _ in 1..10 {
                         thread::sleep(Duration::from_secs(1));
-                        sender.send(indoc! {r#"
-                            event: usermessage
-                            data: {"name": "sasha", "message": "message"}
-
-                        "#})?;
+                        sender.send(
+                            Event::default()
+                                .event("usermessage")
+                                .data(r#"{"name": "sasha", "message": "message"}"#),
+                        )?;
                     }
 
                     thread::sleep(Duration::from_secs(1));
-                    sender.send(indoc! {r#"
-                        event: userdisconnect
-                        data: {"name": "sasha"}
-
-                    "#})?;
+                    sender.send(
+                        Event::default()
+                            .event("userdisconnect")
+                            .data(r#"{"name": "sasha"}"#),
+                    )?;
 
-                    Ok::<_, Box<dyn Error + Send + Sync>>(())
+                    Ok::<_, std::io::Error>(())
                 });
 
-                Response::builder()
-                    .status(StatusCode::OK)
-                    .header("content-type", "text/event-stream")
-                    .body(body)
+                Ok(res)
             }
 
             _ => Response::builder()
                 .status(StatusCode::OK)
                 .header("content-type", "text/html")
-                .body(include_str!("sse.html").into()),
+                .body(Body::from(include_str!("sse.html"))),
         }
     })
 }
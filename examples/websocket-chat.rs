@@ -1,31 +1,33 @@
-use std::{
-    collections::HashMap,
-    error::Error,
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        mpsc::{self, Sender},
-        Arc, Mutex,
-    },
-    thread,
-};
-
-use headers::HeaderMapExt;
-use serde::{Deserialize, Serialize};
-use touche::{upgrade::Upgrade, Body, Connection, Request, Response, Server, StatusCode};
-use tungstenite::{protocol::Role, WebSocket};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-enum Event {
-    #[serde(rename = "user")]
-    User { id: usize },
-    #[serde(rename = "message", rename_all = "camelCase")]
-    Message { user_id: usize, text: String },
-}
-
-type Users = Arc<Mutex<HashMap<usize, Sender<Event>>>>;
-
+#[cfg(feature = "websocket")]
 fn main() -> std::io::Result<()> {
+    use std::{
+        collections::HashMap,
+        error::Error,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Sender},
+            Arc, Mutex,
+        },
+        thread,
+    };
+
+    use serde::{Deserialize, Serialize};
+    use touche::{
+        websocket::{Message, WebSocketUpgrade},
+        Body, Request, Response, Server, StatusCode,
+    };
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    enum Event {
+        #[serde(rename = "user")]
+        User { id: usize },
+        #[serde(rename = "message", rename_all = "camelCase")]
+        Message { user_id: usize, text: String },
+    }
+
+    type Users = Arc<Mutex<HashMap<usize, Sender<Event>>>>;
+
     static NEXT_USER_ID: AtomicUsize = AtomicUsize::new(1);
 
     let users: Users = Arc::new(Mutex::new(HashMap::new()));
@@ -33,11 +35,8 @@ fn main() -> std::io::Result<()> {
     Server::bind("0.0.0.0:4444").serve(move |req: Request<Body>| {
         let users = users.clone();
 
-        if req.headers().typed_get::<headers::Upgrade>().is_some() {
-            let res = tungstenite::handshake::server::create_response(&req.map(|_| ()))?
-                .map(|_| Body::empty());
-
-            Ok::<_, Box<dyn Error + Send + Sync>>(res.upgrade(move |stream: Connection| {
+        if let Ok(upgrade) = WebSocketUpgrade::new(&req) {
+            Ok::<_, Box<dyn Error + Send + Sync>>(upgrade.on_upgrade(move |mut ws| {
                 let users = users.clone();
 
                 let user_id = NEXT_USER_ID.fetch_add(1, Ordering::Relaxed);
@@ -52,31 +51,29 @@ fn main() -> std::io::Result<()> {
                     users.retain(|_id, tx| tx.send(joined_msg.clone()).is_ok());
                 };
 
-                let mut read_ws = WebSocket::from_raw_socket(stream.clone(), Role::Server, None);
-                let mut write_ws = WebSocket::from_raw_socket(stream, Role::Server, None);
+                let sender = ws.sender();
 
                 let write_ws = thread::spawn(move || {
                     for evt in rx {
-                        let msg =
-                            tungstenite::Message::Text(serde_json::to_string(&evt).unwrap().into());
-                        if write_ws.send(msg).is_err() {
+                        let msg = Message::text(serde_json::to_string(&evt).unwrap());
+                        if sender.send(msg).is_err() {
                             break;
                         }
                     }
                 });
 
                 let read_ws = thread::spawn(move || {
-                    while let Ok(msg) = read_ws.read() {
-                        match msg.to_text() {
-                            Ok(text) => {
-                                let text = text.to_owned();
+                    while let Ok(msg) = ws.read() {
+                        match msg {
+                            Message::Text(text) => {
                                 let msg = Event::Message { user_id, text };
                                 users
                                     .lock()
                                     .unwrap()
                                     .retain(|_id, tx| tx.send(msg.clone()).is_ok());
                             }
-                            Err(_) => break,
+                            Message::Close(_) => break,
+                            _ => {}
                         }
                     }
                 });
@@ -92,3 +89,8 @@ fn main() -> std::io::Result<()> {
         }
     })
 }
+
+#[cfg(not(feature = "websocket"))]
+fn main() {
+    println!("This example requires the websocket feature to be enabled");
+}
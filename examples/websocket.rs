@@ -1,20 +1,30 @@
-use std::error::Error;
-
-use touche::{upgrade::Upgrade, Body, Connection, Request, Server};
-use tungstenite::{protocol::Role, WebSocket};
-
+#[cfg(feature = "websocket")]
 fn main() -> std::io::Result<()> {
-    Server::bind("0.0.0.0:4444").serve(|req: Request<Body>| {
-        let res = tungstenite::handshake::server::create_response(&req.map(|_| ()))?;
+    use touche::{
+        websocket::{Message, WebSocketUpgrade},
+        Body, Request, Response, Server, StatusCode,
+    };
 
-        Ok::<_, Box<dyn Error + Send + Sync>>(res.upgrade(|stream: Connection| {
-            let mut ws = WebSocket::from_raw_socket(stream, Role::Server, None);
+    Server::bind("0.0.0.0:4444").serve(|req: Request<Body>| {
+        let Ok(upgrade) = WebSocketUpgrade::new(&req) else {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::empty());
+        };
 
+        Ok(upgrade.on_upgrade(|mut ws| {
             while let Ok(msg) = ws.read() {
-                if msg.is_text() && ws.send(msg).is_err() {
-                    break;
+                if let Message::Text(_) = msg {
+                    if ws.send(msg).is_err() {
+                        break;
+                    }
                 }
             }
         }))
     })
 }
+
+#[cfg(not(feature = "websocket"))]
+fn main() {
+    println!("This example requires the websocket feature to be enabled");
+}
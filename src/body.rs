@@ -56,7 +56,7 @@ impl BodyChannel {
     pub fn send<T: Into<Vec<u8>>>(&self, data: T) -> io::Result<()> {
         self.0
             .send(Ok(data.into().into()))
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "body closed"))
+            .map_err(|_| io::Error::other("body closed"))
     }
 
     /// Send a trailer header. Note that trailers are buffered, and are only sent after the last
@@ -82,14 +82,12 @@ impl BodyChannel {
     pub fn send_trailers(&self, trailers: HeaderMap) -> io::Result<()> {
         self.0
             .send(Ok(Chunk::Trailers(trailers)))
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "body closed"))
+            .map_err(|_| io::Error::other("body closed"))
     }
 
     /// Aborts the body in an abnormal fashion.
     pub fn abort(self) {
-        self.0
-            .send(Err(io::Error::new(io::ErrorKind::Other, "aborted")))
-            .ok();
+        self.0.send(Err(io::Error::other("aborted"))).ok();
     }
 }
 
@@ -129,6 +127,217 @@ impl Body {
     ) -> Self {
         Body(Some(BodyInner::Reader(Box::new(reader), length.into())))
     }
+
+    pub(crate) fn from_chunks(
+        chunks: impl Iterator<Item = io::Result<Chunk>> + Send + 'static,
+    ) -> Self {
+        Body(Some(BodyInner::Iter(Box::new(chunks))))
+    }
+
+    /// Calls `on_end` once the body has been read to its end. Bodies dropped before that are
+    /// drained up to `drain_limit` bytes, and `on_end` is told whether the end was reached.
+    pub(crate) fn on_end(
+        mut self,
+        drain_limit: u64,
+        on_end: impl FnOnce(bool) + Send + 'static,
+    ) -> Self {
+        let mut end = End {
+            drain_limit,
+            callback: Some(Box::new(on_end)),
+        };
+
+        match self.0.take() {
+            Some(BodyInner::Reader(reader, len)) => {
+                let remaining = len.map(|len| len as u64);
+                Body::from_reader(
+                    EndReader {
+                        reader,
+                        remaining,
+                        end,
+                    },
+                    len,
+                )
+            }
+            Some(BodyInner::Iter(chunks)) => Body::from_chunks(EndChunks { chunks, end }),
+            inner => {
+                end.finish(true);
+                Body(inner)
+            }
+        }
+    }
+
+    /// Streams the body through `map`, which receives it as a reader and returns the reader of
+    /// the new body, of unknown length.
+    #[cfg(feature = "client")]
+    pub(crate) fn map_reader<R: Read + Send + 'static>(
+        mut self,
+        map: impl FnOnce(Box<dyn Read + Send>) -> R,
+    ) -> Self {
+        let reader: Box<dyn Read + Send> = match self.0.take() {
+            Some(BodyInner::Empty) | None => Box::new(io::empty()),
+            Some(BodyInner::Buffered(bytes)) => Box::new(Cursor::new(bytes)),
+            Some(BodyInner::Iter(chunks)) => Box::new(ChunksReader {
+                chunks,
+                current: Cursor::new(Vec::new()),
+            }),
+            Some(BodyInner::Reader(reader, Some(len))) => Box::new(reader.take(len as u64)),
+            Some(BodyInner::Reader(reader, None)) => reader,
+        };
+
+        Body::from_reader(map(reader), None)
+    }
+}
+
+/// Reads the data of chunks, skipping their trailers.
+#[cfg(feature = "client")]
+struct ChunksReader {
+    chunks: Box<dyn Iterator<Item = io::Result<Chunk>> + Send>,
+    current: Cursor<Vec<u8>>,
+}
+
+#[cfg(feature = "client")]
+impl Read for ChunksReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            match self.chunks.next() {
+                Some(Ok(Chunk::Data(data))) => self.current = Cursor::new(data),
+                Some(Ok(Chunk::Trailers(_))) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+struct End {
+    drain_limit: u64,
+    callback: Option<Box<dyn FnOnce(bool) + Send>>,
+}
+
+impl End {
+    fn finish(&mut self, reached: bool) {
+        if let Some(callback) = self.callback.take() {
+            callback(reached);
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.callback.is_none()
+    }
+}
+
+struct EndReader {
+    reader: Box<dyn Read + Send>,
+    remaining: Option<u64>,
+    end: End,
+}
+
+impl Read for EndReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = match self.remaining {
+            Some(remaining) => remaining.min(buf.len() as u64) as usize,
+            None => buf.len(),
+        };
+        let buf = &mut buf[..len];
+
+        if buf.is_empty() {
+            if self.remaining == Some(0) {
+                self.end.finish(true);
+            }
+            return Ok(0);
+        }
+
+        match self.reader.read(buf) {
+            Ok(0) => {
+                // Bodies of known length ending early leave the connection in an unknown state
+                self.end.finish(self.remaining.is_none());
+                Ok(0)
+            }
+            Ok(read) => {
+                if let Some(ref mut remaining) = self.remaining {
+                    *remaining -= read as u64;
+                    if *remaining == 0 {
+                        self.end.finish(true);
+                    }
+                }
+                Ok(read)
+            }
+            Err(err) => {
+                self.end.finish(false);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Drop for EndReader {
+    fn drop(&mut self) {
+        if self.end.is_finished() {
+            return;
+        }
+
+        let limit = self.end.drain_limit;
+        let drained = io::copy(&mut self.by_ref().take(limit), &mut io::sink());
+
+        if !self.end.is_finished() {
+            let reached = matches!(drained, Ok(drained) if drained < limit)
+                || matches!(self.read(&mut [0]), Ok(0));
+            self.end.finish(reached);
+        }
+    }
+}
+
+struct EndChunks {
+    chunks: Box<dyn Iterator<Item = io::Result<Chunk>> + Send>,
+    end: End,
+}
+
+impl Iterator for EndChunks {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunks.next();
+
+        let reached = match chunk {
+            None => true,
+            Some(Err(_)) => false,
+            Some(Ok(_)) => return chunk,
+        };
+
+        self.end.finish(reached);
+
+        chunk
+    }
+}
+
+impl Drop for EndChunks {
+    fn drop(&mut self) {
+        if self.end.is_finished() {
+            return;
+        }
+
+        let mut drained = 0;
+        let reached = loop {
+            match self.chunks.next() {
+                None => break true,
+                Some(Err(_)) => break false,
+                Some(Ok(Chunk::Data(data))) => {
+                    drained += data.len() as u64;
+                    if drained > self.end.drain_limit {
+                        break false;
+                    }
+                }
+                Some(Ok(Chunk::Trailers(_))) => {}
+            }
+        };
+
+        self.end.finish(reached);
+    }
 }
 
 impl HttpBody for Body {
@@ -208,21 +417,6 @@ impl HttpBody for Body {
     }
 }
 
-impl Drop for Body {
-    fn drop(&mut self) {
-        #[allow(unused_must_use)]
-        match self.0.take() {
-            Some(BodyInner::Reader(ref mut stream, Some(len))) => {
-                io::copy(&mut stream.take(len as u64), &mut io::sink());
-            }
-            Some(BodyInner::Reader(ref mut stream, None)) => {
-                io::copy(stream, &mut io::sink());
-            }
-            _ => {}
-        }
-    }
-}
-
 impl From<Vec<u8>> for Body {
     fn from(body: Vec<u8>) -> Self {
         Body(Some(BodyInner::Buffered(body)))
@@ -253,7 +447,7 @@ impl TryFrom<File> for Body {
     fn try_from(file: File) -> Result<Self, Self::Error> {
         match file.metadata() {
             Ok(meta) if meta.is_file() => Ok(Body::from_reader(file, meta.len() as usize)),
-            Ok(_) => Err(io::Error::new(io::ErrorKind::Other, "not a file")),
+            Ok(_) => Err(io::Error::other("not a file")),
             Err(err) => Err(err),
         }
     }
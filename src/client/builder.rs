@@ -0,0 +1,306 @@
+use headers::{authorization::Basic, Authorization, HeaderMapExt};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderName, HeaderValue, Method, Uri,
+};
+
+use super::{Client, RequestError};
+use crate::Body;
+
+/// Builds a request to send with a [`Client`], started with one of its methods such as
+/// [`Client::get`] or [`Client::post`].
+///
+/// Errors on any of the steps, such as invalid header values, are reported once the request is
+/// sent.
+///
+/// # Example
+/// ```no_run
+/// use touche::{client::ResponseExt, Client};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new();
+///
+/// let res = client
+///     .get("http://example.com/search")
+///     .query([("q", "touché"), ("page", "2")])
+///     .bearer_auth("secret-token")
+///     .send()?
+///     .error_for_status()?;
+///
+/// println!("{}", res.text()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use = "requests are only sent by calling `send`"]
+pub struct RequestBuilder {
+    client: Client,
+    request: Result<http::request::Builder, RequestError>,
+    body: Body,
+}
+
+impl Client {
+    /// Starts a `GET` request to the URI.
+    pub fn get<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::GET, uri)
+    }
+
+    /// Starts a `POST` request to the URI.
+    pub fn post<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::POST, uri)
+    }
+
+    /// Starts a `PUT` request to the URI.
+    pub fn put<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::PUT, uri)
+    }
+
+    /// Starts a `PATCH` request to the URI.
+    pub fn patch<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::PATCH, uri)
+    }
+
+    /// Starts a `DELETE` request to the URI.
+    pub fn delete<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::DELETE, uri)
+    }
+
+    /// Starts a `HEAD` request to the URI.
+    pub fn head<U>(&self, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.request_builder(Method::HEAD, uri)
+    }
+
+    /// Starts a request to the URI with any method.
+    pub fn request_builder<U>(&self, method: Method, uri: U) -> RequestBuilder
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        RequestBuilder {
+            client: self.clone(),
+            request: Ok(http::Request::builder().method(method).uri(uri)),
+            body: Body::empty(),
+        }
+    }
+}
+
+impl RequestBuilder {
+    /// Adds the pairs to the query of the URI, encoded as `application/x-www-form-urlencoded`.
+    pub fn query<K, V>(self, pairs: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.and_then(|request| {
+            let uri = request.uri_ref().cloned().unwrap_or_default();
+            let query = match (uri.query(), form_urlencoded(pairs)) {
+                (_, added) if added.is_empty() => return Ok(request),
+                (Some(query), added) if !query.is_empty() => format!("{query}&{added}"),
+                (_, added) => added,
+            };
+
+            let mut parts = uri.into_parts();
+            let path = parts
+                .path_and_query
+                .as_ref()
+                .map_or("/", |path_and_query| path_and_query.path());
+            parts.path_and_query = Some(
+                format!("{path}?{query}")
+                    .parse()
+                    .map_err(|_| RequestError::InvalidUri)?,
+            );
+
+            let uri = Uri::from_parts(parts).map_err(|_| RequestError::InvalidUri)?;
+            Ok(request.uri(uri))
+        })
+    }
+
+    /// Appends a header to the request.
+    pub fn header<K, V>(self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Into<http::Error>,
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        self.and_then(|request| Ok(request.header(name, value)))
+    }
+
+    /// Authenticates with a bearer token, on the `Authorization` header.
+    pub fn bearer_auth(self, token: impl AsRef<str>) -> Self {
+        self.and_then(|request| {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token.as_ref()))
+                .map_err(http::Error::from)?;
+            value.set_sensitive(true);
+            Ok(request.header(AUTHORIZATION, value))
+        })
+    }
+
+    /// Authenticates with a username and password, on the `Authorization` header.
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.and_then(|mut request| {
+            if let Some(headers) = request.headers_mut() {
+                headers.typed_insert(Authorization::<Basic>::basic(username, password));
+                if let Some(value) = headers.get_mut(AUTHORIZATION) {
+                    value.set_sensitive(true);
+                }
+            }
+            Ok(request)
+        })
+    }
+
+    /// Adds an extension to the request, such as a [`UnixSocket`](super::UnixSocket) to send it
+    /// to.
+    pub fn extension<T>(self, extension: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.and_then(|request| Ok(request.extension(extension)))
+    }
+
+    /// Sets the body of the request.
+    pub fn body(self, body: impl Into<Body>) -> Self {
+        Self {
+            body: body.into(),
+            ..self
+        }
+    }
+
+    /// Sends the pairs as an `application/x-www-form-urlencoded` body.
+    pub fn form<K, V>(self, pairs: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(form_urlencoded(pairs))
+    }
+
+    /// Serializes the value as an `application/json` body.
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::Serialize + ?Sized>(self, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => self.header(CONTENT_TYPE, "application/json").body(body),
+            Err(err) => Self {
+                request: Err(RequestError::Json(err)),
+                ..self
+            },
+        }
+    }
+
+    /// Builds the request without sending it.
+    pub fn build(self) -> Result<http::Request<Body>, RequestError> {
+        Ok(self.request?.body(self.body)?)
+    }
+
+    /// Sends the request with [`Client::request`].
+    pub fn send(self) -> Result<http::Response<Body>, RequestError> {
+        let req = self.request?.body(self.body)?;
+        self.client.request(req)
+    }
+
+    fn and_then(
+        self,
+        f: impl FnOnce(http::request::Builder) -> Result<http::request::Builder, RequestError>,
+    ) -> Self {
+        Self {
+            request: self.request.and_then(f),
+            ..self
+        }
+    }
+}
+
+/// Encodes the pairs as `application/x-www-form-urlencoded`.
+fn form_urlencoded<K, V>(pairs: impl IntoIterator<Item = (K, V)>) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let encode = |input: &str| {
+        input.bytes().fold(String::new(), |mut encoded, byte| {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                    encoded.push(byte as char)
+                }
+                b' ' => encoded.push('+'),
+                byte => encoded.push_str(&format!("%{byte:02X}")),
+            }
+            encoded
+        })
+    };
+
+    pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", encode(key.as_ref()), encode(value.as_ref())))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_requests() {
+        let client = Client::new();
+
+        let req = client
+            .post("http://example.com/search?lang=fr")
+            .query([("q", "touché & co"), ("page", "2")])
+            .header("x-request-id", "1")
+            .bearer_auth("token")
+            .form([("name", "a+b")])
+            .build()
+            .unwrap();
+
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(
+            req.uri(),
+            "http://example.com/search?lang=fr&q=touch%C3%A9+%26+co&page=2"
+        );
+        assert_eq!(req.headers()["x-request-id"], "1");
+        assert_eq!(req.headers()[AUTHORIZATION], "Bearer token");
+        assert!(req.headers()[AUTHORIZATION].is_sensitive());
+        assert_eq!(
+            req.headers()[CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            crate::HttpBody::into_bytes(req.into_body()).unwrap(),
+            b"name=a%2Bb"
+        );
+
+        let req = client.get("http://example.com").query([("a", "1")]).build();
+        assert_eq!(req.unwrap().uri(), "http://example.com/?a=1");
+
+        let req = client
+            .get("http://example.com")
+            .header("x-bad", "\n")
+            .build();
+        assert!(matches!(req, Err(RequestError::Http(_))));
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use http::Uri;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+use super::timeout::{limit, Elapsed, Timeouts};
+use crate::Connection;
+
+/// Opens the connections of a [`Client`](super::Client), replacing its own way of connecting.
+///
+/// The connector receives the scheme and authority of the requests, and must return a connection
+/// ready to send them, which for HTTPS means already speaking TLS. Connections are pooled as usual,
+/// but proxies are not used.
+///
+/// It is implemented for closures, so tests can point any host at a local server:
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use touche::{Client, Connection};
+///
+/// let client = Client::builder()
+///     .connector(|_uri: &http::Uri| Ok(Connection::from(TcpStream::connect("127.0.0.1:8080")?)))
+///     .build();
+/// ```
+pub trait Connect: Send + Sync + 'static {
+    fn connect(&self, uri: &Uri) -> io::Result<Connection>;
+}
+
+impl<F> Connect for F
+where
+    F: Fn(&Uri) -> io::Result<Connection> + Send + Sync + 'static,
+{
+    fn connect(&self, uri: &Uri) -> io::Result<Connection> {
+        self(uri)
+    }
+}
+
+impl fmt::Debug for dyn Connect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Connect")
+    }
+}
+
+/// Resolves host names into the addresses the [`Client`](super::Client) connects to.
+///
+/// It is implemented for closures, and defaults to the resolver of the operating system.
+pub trait Resolve: Send + Sync + 'static {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+impl<F> Resolve for F
+where
+    F: Fn(&str, u16) -> io::Result<Vec<SocketAddr>> + Send + Sync + 'static,
+{
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        self(host, port)
+    }
+}
+
+impl fmt::Debug for dyn Resolve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolve")
+    }
+}
+
+#[derive(Debug)]
+struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        Ok((host, port).to_socket_addrs()?.collect())
+    }
+}
+
+/// Opens TCP connections, resolving host names and racing their addresses.
+#[derive(Debug, Clone)]
+pub(crate) struct Dialer {
+    pub(crate) resolver: Arc<dyn Resolve>,
+    pub(crate) overrides: HashMap<String, Vec<IpAddr>>,
+    pub(crate) happy_eyeballs_delay: Option<Duration>,
+    pub(crate) local_address: Option<IpAddr>,
+}
+
+impl Default for Dialer {
+    fn default() -> Self {
+        Self {
+            resolver: Arc::new(SystemResolver),
+            overrides: HashMap::new(),
+            happy_eyeballs_delay: Some(Duration::from_millis(250)),
+            local_address: None,
+        }
+    }
+}
+
+impl Dialer {
+    /// Opens a TCP connection to one of the addresses `host` resolves to.
+    ///
+    /// Addresses are tried alternating between IPv6 and IPv4 (Happy Eyeballs). Each attempt that
+    /// doesn't connect within the delay is left running while the next one starts, and the first
+    /// one to connect wins.
+    pub(crate) fn dial(
+        &self,
+        host: &str,
+        port: u16,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+    ) -> io::Result<TcpStream> {
+        let addrs = self.resolve(host, port)?;
+
+        match self.happy_eyeballs_delay {
+            Some(delay) if addrs.len() > 1 => self.race(addrs, delay, timeouts, deadline),
+            _ => self.try_in_order(addrs, timeouts, deadline),
+        }
+    }
+
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        // Addresses of another family than the local one can't be reached from it
+        let addrs = self
+            .lookup(host, port)?
+            .into_iter()
+            .filter(|addr| {
+                self.local_address
+                    .is_none_or(|local| local.is_ipv4() == addr.is_ipv4())
+            })
+            .collect::<Vec<_>>();
+
+        if addrs.is_empty() {
+            return Err(io::Error::other("could not resolve to any address"));
+        }
+
+        Ok(interleave(addrs))
+    }
+
+    /// Resolves `host` with the static overrides first, then the resolver, unless it is an IP
+    /// address already.
+    pub(crate) fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        // IPv6 hosts are bracketed on URIs
+        let host = host.trim_matches(['[', ']']);
+
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            Ok(vec![SocketAddr::new(ip, port)])
+        } else if let Some(ips) = self.overrides.get(&host.to_ascii_lowercase()) {
+            Ok(ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect())
+        } else {
+            self.resolver.resolve(host, port)
+        }
+    }
+
+    fn try_in_order(
+        &self,
+        addrs: Vec<SocketAddr>,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+    ) -> io::Result<TcpStream> {
+        let mut last_err = None;
+
+        for addr in addrs {
+            match connect_addr(addr, self.local_address, timeouts, deadline) {
+                Ok(tcp) => return Ok(tcp),
+                Err(err) if Elapsed::find(&err) == Some(Elapsed::Deadline) => return Err(err),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| io::Error::other("could not resolve to any address")))
+    }
+
+    fn race(
+        &self,
+        addrs: Vec<SocketAddr>,
+        delay: Duration,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+    ) -> io::Result<TcpStream> {
+        let (tx, rx) = mpsc::channel();
+        let mut addrs = addrs.into_iter();
+        let mut pending = 0;
+        let mut last_err = None;
+
+        loop {
+            if let Some(addr) = addrs.next() {
+                let tx = tx.clone();
+                let local_address = self.local_address;
+                let timeouts = *timeouts;
+                thread::spawn(move || {
+                    tx.send(connect_addr(addr, local_address, &timeouts, deadline))
+                        .ok();
+                });
+                pending += 1;
+            } else if pending == 0 {
+                break;
+            }
+
+            // Waits for an attempt to finish, or for the delay to start the next one
+            let attempt = match addrs.len() {
+                0 => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                _ => rx.recv_timeout(delay),
+            };
+
+            match attempt {
+                Ok(Ok(tcp)) => return Ok(tcp),
+                Ok(Err(err)) if Elapsed::find(&err) == Some(Elapsed::Deadline) => return Err(err),
+                Ok(Err(err)) => {
+                    pending -= 1;
+                    last_err = Some(err);
+                }
+                Err(_) => {}
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| io::Error::other("could not resolve to any address")))
+    }
+}
+
+fn connect_addr(
+    addr: SocketAddr,
+    local_address: Option<IpAddr>,
+    timeouts: &Timeouts,
+    deadline: Option<Instant>,
+) -> io::Result<TcpStream> {
+    let (timeout, elapsed) = limit(timeouts.connect, Elapsed::Connect, deadline)?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+    if let Some(local_address) = local_address {
+        socket.bind(&SockAddr::from(SocketAddr::new(local_address, 0)))?;
+    }
+
+    let addr = SockAddr::from(addr);
+    match timeout {
+        Some(timeout) => socket
+            .connect_timeout(&addr, timeout)
+            .map_err(|err| elapsed.or(err))?,
+        None => socket.connect(&addr)?,
+    }
+
+    Ok(socket.into())
+}
+
+/// Alternates between address families, starting with the one of the first address.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(ipv4_first) = addrs.first().map(SocketAddr::is_ipv4) else {
+        return addrs;
+    };
+
+    let (preferred, others): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv4() == ipv4_first);
+
+    let mut preferred = preferred.into_iter();
+    let mut others = others.into_iter();
+    let mut addrs = Vec::with_capacity(preferred.len() + others.len());
+
+    loop {
+        match (preferred.next(), others.next()) {
+            (None, None) => return addrs,
+            (a, b) => addrs.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn interleaves_address_families() {
+        let addr = |addr: &str| addr.parse::<SocketAddr>().unwrap();
+
+        assert_eq!(
+            interleave(vec![
+                addr("[::1]:80"),
+                addr("[::2]:80"),
+                addr("[::3]:80"),
+                addr("127.0.0.1:80"),
+                addr("127.0.0.2:80"),
+            ]),
+            [
+                addr("[::1]:80"),
+                addr("127.0.0.1:80"),
+                addr("[::2]:80"),
+                addr("127.0.0.2:80"),
+                addr("[::3]:80"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_static_overrides_before_the_resolver() {
+        let dialer = Dialer {
+            resolver: Arc::new(|_: &str, _| Err(io::Error::other("no dns here"))),
+            overrides: HashMap::from([(
+                "example.com".to_string(),
+                vec!["10.0.0.1".parse().unwrap()],
+            )]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            dialer.resolve("EXAMPLE.com", 8080).unwrap(),
+            ["10.0.0.1:8080".parse().unwrap()]
+        );
+        assert_eq!(
+            dialer.resolve("[::1]", 80).unwrap(),
+            ["[::1]:80".parse().unwrap()]
+        );
+        assert!(dialer.resolve("example.org", 80).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_the_next_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Nothing listens on the first one
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let addrs = vec![closed, listener.local_addr().unwrap()];
+        let dialer = Dialer {
+            resolver: Arc::new(move |_: &str, _| Ok(addrs.clone())),
+            ..Default::default()
+        };
+
+        let tcp = dialer
+            .dial("example.com", port, &Timeouts::default(), None)
+            .unwrap();
+        assert_eq!(tcp.peer_addr().unwrap(), listener.local_addr().unwrap());
+
+        let dialer = Dialer {
+            happy_eyeballs_delay: None,
+            ..dialer
+        };
+        let tcp = dialer
+            .dial("example.com", port, &Timeouts::default(), None)
+            .unwrap();
+        assert_eq!(tcp.peer_addr().unwrap(), listener.local_addr().unwrap());
+    }
+}
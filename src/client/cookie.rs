@@ -0,0 +1,475 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    net::IpAddr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use headers::Header;
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    uri::Scheme,
+    HeaderMap, HeaderValue, Uri,
+};
+
+/// Stores the cookies set by servers, and sends them back on the requests they apply to.
+///
+/// `Set-Cookie` headers are parsed following [RFC 6265](https://www.rfc-editor.org/rfc/rfc6265),
+/// honoring their `Domain`, `Path`, `Secure`, `Expires` and `Max-Age` attributes. There is no
+/// public suffix list, so cookies are only refused for domains without any dot, such as `com`.
+///
+/// Jars can be saved to and loaded from files in the Netscape format used by curl and wget.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use touche::{client::CookieJar, Client};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let jar = Arc::new(CookieJar::load("cookies.txt").unwrap_or_default());
+/// let client = Client::builder().cookie_jar(jar.clone()).build();
+///
+/// client.request(
+///     http::Request::post("https://example.com/login")
+///         .header("content-type", "application/x-www-form-urlencoded")
+///         .body("user=me&password=secret")?,
+/// )?;
+/// client.request(http::Request::get("https://example.com/api/me").body(())?)?;
+///
+/// jar.save("cookies.txt")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct CookieJar(Mutex<Vec<Cookie>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    expires: Option<SystemTime>,
+}
+
+/// Skips the cookie jar of the [`Client`](super::Client) for a request, when added as one of its
+/// extensions. Cookies are neither sent with it nor stored from its response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoCookies;
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the cookies from a file in the Netscape format. Expired cookies are skipped.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let now = SystemTime::now();
+        let cookies = fs::read_to_string(path)?
+            .lines()
+            .filter_map(Cookie::from_line)
+            .filter(|cookie| !cookie.is_expired(now))
+            .collect();
+
+        Ok(Self(Mutex::new(cookies)))
+    }
+
+    /// Saves the cookies to a file in the Netscape format. Session cookies, which have no expiry,
+    /// are saved as well.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let now = SystemTime::now();
+        let mut file = String::from("# Netscape HTTP Cookie File\n");
+
+        for cookie in self.lock().iter().filter(|cookie| !cookie.is_expired(now)) {
+            cookie.write_line(&mut file);
+        }
+
+        fs::write(path, file)
+    }
+
+    /// The `Cookie` header to send with a request to the URI, if any cookie applies to it.
+    pub fn cookies(&self, uri: &Uri) -> Option<HeaderValue> {
+        let host = uri.host()?.to_ascii_lowercase();
+        let path = uri.path();
+        let secure = uri.scheme() == Some(&Scheme::HTTPS);
+        let now = SystemTime::now();
+
+        let mut cookies = self.lock();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let mut matching = cookies
+            .iter()
+            .filter(|cookie| match cookie.host_only {
+                true => host == cookie.domain,
+                false => domain_matches(&host, &cookie.domain),
+            })
+            .filter(|cookie| path_matches(path, &cookie.path))
+            .filter(|cookie| secure || !cookie.secure)
+            .collect::<Vec<_>>();
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        // Cookies with longer paths go first, otherwise they keep the order they were set in
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+
+        let header = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        HeaderValue::from_str(&header).ok()
+    }
+
+    /// Stores the cookies set by the `Set-Cookie` headers of a response to the URI.
+    pub fn set_cookies(&self, uri: &Uri, headers: &HeaderMap) {
+        let now = SystemTime::now();
+        let mut cookies = self.lock();
+
+        for header in headers.get_all(SET_COOKIE) {
+            let Some(cookie) = header
+                .to_str()
+                .ok()
+                .and_then(|header| Cookie::parse(header, uri, now))
+            else {
+                continue;
+            };
+
+            let existing = cookies.iter().position(|other| {
+                other.name == cookie.name
+                    && other.domain == cookie.domain
+                    && other.path == cookie.path
+            });
+
+            // Replaced cookies keep their place, and servers remove cookies by expiring them
+            match existing {
+                Some(i) if cookie.is_expired(now) => {
+                    cookies.remove(i);
+                }
+                Some(i) => cookies[i] = cookie,
+                None if cookie.is_expired(now) => {}
+                None => cookies.push(cookie),
+            }
+        }
+    }
+
+    /// Adds the cookies for the URI to the `Cookie` header, after any already set on it.
+    pub(crate) fn add_cookie_header(&self, uri: &Uri, headers: &mut HeaderMap) {
+        let Some(cookies) = self.cookies(uri) else {
+            return;
+        };
+
+        let header = match headers.get(COOKIE) {
+            Some(existing) => {
+                HeaderValue::from_bytes(&[existing.as_bytes(), b"; ", cookies.as_bytes()].concat())
+                    .unwrap_or(cookies)
+            }
+            None => cookies,
+        };
+
+        headers.insert(COOKIE, header);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Cookie>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header received from the URI, following
+    /// [RFC 6265 section 5.2](https://www.rfc-editor.org/rfc/rfc6265#section-5.2).
+    fn parse(header: &str, uri: &Uri, now: SystemTime) -> Option<Self> {
+        let host = uri.host()?.to_ascii_lowercase();
+        let mut attributes = header.split(';');
+
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(uri.path()),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+        let mut max_age = None;
+
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "expires" => cookie.expires = parse_date(value).or(cookie.expires),
+                "max-age" => {
+                    if let Ok(seconds) = value.parse::<i64>() {
+                        max_age = Some(match u64::try_from(seconds) {
+                            Ok(seconds) if seconds > 0 => expiry_after(now, seconds),
+                            _ => UNIX_EPOCH,
+                        });
+                    }
+                }
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+
+                    if domain == host {
+                        cookie.host_only = false;
+                    } else if !domain.contains('.') || !domain_matches(&host, &domain) {
+                        return None;
+                    } else {
+                        cookie.domain = domain;
+                        cookie.host_only = false;
+                    }
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        cookie.expires = max_age.or(cookie.expires);
+
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Parses a line of a Netscape cookie file.
+    fn from_line(line: &str) -> Option<Self> {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None if line.starts_with('#') => return None,
+            None => (line, false),
+        };
+
+        let [domain, subdomains, path, secure, expires, name, value] = line
+            .trim_end_matches(['\r', '\n'])
+            .splitn(7, '\t')
+            .collect::<Vec<_>>()
+            .try_into()
+            .ok()?;
+
+        Some(Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: !subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires: match expires.parse::<u64>().ok()? {
+                0 => None,
+                seconds => Some(expiry_after(UNIX_EPOCH, seconds)),
+            },
+        })
+    }
+
+    fn write_line(&self, file: &mut String) {
+        let flag = |flag: bool| if flag { "TRUE" } else { "FALSE" };
+        let expires = self
+            .expires
+            .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |expires| expires.as_secs());
+
+        writeln!(
+            file,
+            "{}{}{}\t{}\t{}\t{}\t{expires}\t{}\t{}",
+            if self.http_only { "#HttpOnly_" } else { "" },
+            if self.host_only { "" } else { "." },
+            self.domain,
+            flag(!self.host_only),
+            self.path,
+            flag(self.secure),
+            self.name,
+            self.value,
+        )
+        .ok();
+    }
+}
+
+/// Whether the host is the domain or one of its subdomains. IP addresses only match themselves.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.trim_matches(['[', ']']).parse::<IpAddr>().is_err()
+            && host
+                .strip_suffix(domain)
+                .is_some_and(|subdomain| subdomain.ends_with('.')))
+}
+
+/// Whether the cookie path is the request path or one of its parent directories.
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The directory of the request path, used for cookies without a `Path` attribute.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+/// Adds the seconds to the time, clamping the expiry to the end of year 9999, the latest time
+/// cookie dates can be written with.
+fn expiry_after(time: SystemTime, seconds: u64) -> SystemTime {
+    let latest = UNIX_EPOCH + Duration::from_secs(253_402_300_799);
+
+    time.checked_add(Duration::from_secs(seconds))
+        .map_or(latest, |expires| expires.min(latest))
+}
+
+/// Parses the `Expires` date, also accepting the dashes between its parts used by old servers.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let decode = |value: &str| {
+        let value = HeaderValue::from_str(value).ok()?;
+        headers::Date::decode(&mut std::iter::once(&value)).ok()
+    };
+
+    decode(value)
+        .or_else(|| decode(&value.replace('-', " ")))
+        .map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jar_with(uri: &str, set_cookies: &[&str]) -> CookieJar {
+        let jar = CookieJar::new();
+        let mut headers = HeaderMap::new();
+        for set_cookie in set_cookies {
+            headers.append(SET_COOKIE, set_cookie.parse().unwrap());
+        }
+        jar.set_cookies(&uri.parse().unwrap(), &headers);
+        jar
+    }
+
+    fn cookies(jar: &CookieJar, uri: &str) -> Option<String> {
+        jar.cookies(&uri.parse().unwrap())
+            .map(|header| header.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn matches_cookies_by_domain_and_path() {
+        let jar = jar_with(
+            "http://www.example.com/account/login",
+            &[
+                "session=abc; Path=/",
+                "prefs=dark; Domain=.example.com",
+                "step=2",
+                "other=1; Domain=example.org",
+                "tld=1; Domain=com",
+            ],
+        );
+
+        assert_eq!(
+            cookies(&jar, "http://www.example.com/account/settings").as_deref(),
+            Some("prefs=dark; step=2; session=abc")
+        );
+        assert_eq!(
+            cookies(&jar, "http://api.example.com/account/").as_deref(),
+            Some("prefs=dark")
+        );
+        assert_eq!(
+            cookies(&jar, "http://www.example.com/").as_deref(),
+            Some("session=abc")
+        );
+        assert_eq!(cookies(&jar, "http://example.org/"), None);
+        assert_eq!(cookies(&jar, "http://www.com/"), None);
+    }
+
+    #[test]
+    fn honors_secure_and_expiry_attributes() {
+        let jar = jar_with(
+            "https://example.com/",
+            &[
+                "token=1; Secure",
+                "old=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                "legacy=1; Expires=Wed, 21-Oct-2099 07:28:00 GMT",
+                "gone=1; Max-Age=0; Expires=Wed, 21 Oct 2099 07:28:00 GMT",
+                "kept=1; Max-Age=3600",
+            ],
+        );
+
+        assert_eq!(
+            cookies(&jar, "https://example.com/").as_deref(),
+            Some("token=1; legacy=1; kept=1")
+        );
+        assert_eq!(
+            cookies(&jar, "http://example.com/").as_deref(),
+            Some("legacy=1; kept=1")
+        );
+
+        jar.set_cookies(
+            &"https://example.com/".parse().unwrap(),
+            &HeaderMap::from_iter([(SET_COOKIE, "kept=; Max-Age=-1".parse().unwrap())]),
+        );
+        assert_eq!(
+            cookies(&jar, "http://example.com/").as_deref(),
+            Some("legacy=1")
+        );
+    }
+
+    #[test]
+    fn clamps_expiry_times_too_far_in_the_future() {
+        let jar = jar_with(
+            "http://example.com/",
+            &["forever=1; Max-Age=9223372036854775807"],
+        );
+        assert_eq!(
+            cookies(&jar, "http://example.com/").as_deref(),
+            Some("forever=1")
+        );
+
+        let cookie = Cookie::from_line("example.com\tFALSE\t/\tFALSE\t18446744073709551615\tn\tv");
+        assert_eq!(cookie.unwrap().expires, jar.lock()[0].expires);
+    }
+
+    #[test]
+    fn saves_and_loads_cookie_files() {
+        let jar = jar_with(
+            "https://www.example.com/app/",
+            &[
+                "session=abc; HttpOnly; Secure",
+                "prefs=dark; Domain=example.com; Path=/; Max-Age=3600",
+            ],
+        );
+
+        let path = std::env::temp_dir().join(format!("touche-cookies-{}", std::process::id()));
+        jar.save(&path).unwrap();
+        let loaded = CookieJar::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut saved = jar.lock().clone();
+        let mut loaded = loaded.lock().clone();
+        // Files only keep whole seconds
+        for cookie in saved.iter_mut().chain(loaded.iter_mut()) {
+            cookie.expires = cookie.expires.map(|expires| {
+                UNIX_EPOCH
+                    + Duration::from_secs(expires.duration_since(UNIX_EPOCH).unwrap().as_secs())
+            });
+        }
+        assert_eq!(saved, loaded);
+        assert_eq!(loaded[0].path, "/app");
+        assert!(loaded[0].host_only && loaded[0].http_only && loaded[0].secure);
+        assert!(!loaded[1].host_only);
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::Read;
+#[cfg(feature = "deflate")]
+use std::io::{BufRead, BufReader};
+
+use http::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    HeaderValue, Method, Response,
+};
+
+use crate::{response, Body, HttpBody};
+
+/// Gets the raw body of a response from the [`Client`](super::Client), when added as an extension
+/// of the request.
+///
+/// Otherwise, the client asks for compressed responses with the `Accept-Encoding` header, and
+/// decodes the bodies compressed with the encodings enabled through the `gzip`, `deflate` and
+/// `brotli` features. Requests that set their own `Accept-Encoding` header also get raw bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoDecompression;
+
+/// Tells that the body of a response was decompressed by the [`Client`](super::Client), available
+/// as an extension of the response.
+///
+/// The `Content-Encoding` and `Content-Length` headers are removed from decompressed responses,
+/// since they no longer apply to the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decompressed(HeaderValue);
+
+impl Decompressed {
+    /// The encoding the body was decompressed from, as sent on the `Content-Encoding` header.
+    pub fn encoding(&self) -> &HeaderValue {
+        &self.0
+    }
+}
+
+/// The `Accept-Encoding` header for the enabled encodings, if any is.
+pub(crate) fn accept_encoding() -> Option<HeaderValue> {
+    let encodings: &[&str] = &[
+        #[cfg(feature = "gzip")]
+        "gzip",
+        #[cfg(feature = "deflate")]
+        "deflate",
+        #[cfg(feature = "brotli")]
+        "br",
+    ];
+
+    match encodings.is_empty() {
+        true => None,
+        false => HeaderValue::from_str(&encodings.join(", ")).ok(),
+    }
+}
+
+/// Decompresses the body of the response as it is read, if it has a supported encoding.
+///
+/// Responses without a body, such as a `304 Not Modified` or the response to a `HEAD`, are left
+/// untouched, since their headers describe a body that was not sent.
+pub(crate) fn decompress(method: &Method, res: Response<Body>) -> Response<Body> {
+    if !response::has_body(method, &res) || res.body().is_empty() {
+        return res;
+    }
+
+    let Some(encoding) = res.headers().get(CONTENT_ENCODING).cloned() else {
+        return res;
+    };
+
+    let Some(decoder) = decoder(&encoding.as_bytes().trim_ascii().to_ascii_lowercase()) else {
+        return res;
+    };
+
+    let (mut parts, body) = res.into_parts();
+    let body = body.map_reader(decoder);
+
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.extensions.insert(Decompressed(encoding));
+
+    Response::from_parts(parts, body)
+}
+
+type Decoder = fn(Box<dyn Read + Send>) -> Box<dyn Read + Send>;
+
+fn decoder(encoding: &[u8]) -> Option<Decoder> {
+    match encoding {
+        #[cfg(feature = "gzip")]
+        b"gzip" | b"x-gzip" => Some(|reader| Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "deflate")]
+        b"deflate" => Some(inflate),
+        #[cfg(feature = "brotli")]
+        b"br" => Some(|reader| Box::new(brotli_decompressor::Decompressor::new(reader, 8 * 1024))),
+        _ => None,
+    }
+}
+
+/// Decodes `deflate` bodies, which should be zlib streams but are sent as raw deflate ones by
+/// some servers.
+#[cfg(feature = "deflate")]
+fn inflate(reader: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+    let mut reader = BufReader::new(reader);
+
+    let is_zlib = match reader.fill_buf() {
+        Ok([cmf, flg, ..]) => cmf & 0x0f == 8 && u16::from_be_bytes([*cmf, *flg]) % 31 == 0,
+        _ => true,
+    };
+
+    match is_zlib {
+        true => Box::new(flate2::bufread::ZlibDecoder::new(reader)),
+        false => Box::new(flate2::bufread::DeflateDecoder::new(reader)),
+    }
+}
+
+#[cfg(all(test, any(feature = "gzip", feature = "deflate", feature = "brotli")))]
+mod tests {
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    use std::io::Write;
+
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    use flate2::{write, Compression};
+
+    use super::*;
+    use crate::HttpBody;
+
+    fn compressed(encoding: &str, body: Vec<u8>) -> Response<Body> {
+        Response::builder()
+            .header(CONTENT_ENCODING, encoding)
+            .header(CONTENT_LENGTH, body.len())
+            .body(Body::from_iter([body]))
+            .unwrap()
+    }
+
+    #[test]
+    fn decompresses_bodies() {
+        let mut cases = Vec::new();
+
+        #[cfg(feature = "gzip")]
+        {
+            let mut gzip = write::GzEncoder::new(Vec::new(), Compression::fast());
+            gzip.write_all(b"Hello world").unwrap();
+            cases.push(("gzip", gzip.finish().unwrap()));
+        }
+
+        #[cfg(feature = "deflate")]
+        {
+            let mut zlib = write::ZlibEncoder::new(Vec::new(), Compression::fast());
+            zlib.write_all(b"Hello world").unwrap();
+            cases.push(("deflate", zlib.finish().unwrap()));
+
+            let mut raw = write::DeflateEncoder::new(Vec::new(), Compression::fast());
+            raw.write_all(b"Hello world").unwrap();
+            cases.push(("Deflate", raw.finish().unwrap()));
+        }
+
+        // A single uncompressed meta-block, as brotli has no encoder here
+        #[cfg(feature = "brotli")]
+        cases.push(("br", [&[0xa0, 0, 0x10][..], b"Hello world", &[3]].concat()));
+
+        for (encoding, body) in cases {
+            let res = decompress(&Method::GET, compressed(encoding, body));
+            assert!(!res.headers().contains_key(CONTENT_ENCODING));
+            assert!(!res.headers().contains_key(CONTENT_LENGTH));
+            assert_eq!(
+                res.extensions().get::<Decompressed>().unwrap().encoding(),
+                encoding
+            );
+            assert_eq!(res.into_body().into_bytes().unwrap(), b"Hello world");
+        }
+    }
+
+    #[test]
+    fn skips_responses_without_body() {
+        let encoding = accept_encoding().unwrap();
+        let encoding = encoding.to_str().unwrap().split(',').next().unwrap();
+
+        let not_modified = Response::builder()
+            .status(http::StatusCode::NOT_MODIFIED)
+            .header(CONTENT_ENCODING, encoding)
+            .body(Body::empty())
+            .unwrap();
+
+        let head = Response::builder()
+            .header(CONTENT_ENCODING, encoding)
+            .header(CONTENT_LENGTH, 11)
+            .body(Body::empty())
+            .unwrap();
+
+        for (method, res) in [(Method::GET, not_modified), (Method::HEAD, head)] {
+            let res = decompress(&method, res);
+            assert_eq!(res.headers()[CONTENT_ENCODING], encoding);
+            assert!(res.extensions().get::<Decompressed>().is_none());
+            assert_eq!(res.into_body().into_bytes().unwrap(), b"");
+        }
+
+        let res = decompress(&Method::HEAD, compressed(encoding, b"Hello world".to_vec()));
+        assert_eq!(res.headers()[CONTENT_LENGTH], "11");
+    }
+
+    #[test]
+    fn keeps_unsupported_encodings() {
+        let res = decompress(&Method::GET, compressed("zstd", b"raw".to_vec()));
+        assert_eq!(res.headers()[CONTENT_ENCODING], "zstd");
+        assert!(res.extensions().get::<Decompressed>().is_none());
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"raw");
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use http::{Method, StatusCode};
+
+use super::{closes_connection, into_io, is_closed};
+use crate::{request, response, Body, Connection, HttpBody};
+
+/// Sends requests back to back on a single connection, without waiting for the response to each
+/// one before writing the next, as allowed by
+/// [HTTP/1.1 pipelining](https://www.rfc-editor.org/rfc/rfc9112#section-9.3.2).
+///
+/// The pipeline iterates over the responses in the order of their requests. At most `max_depth`
+/// requests wait for their response at any time, and queued ones are written as responses
+/// arrive. Response bodies are read whole before being yielded, since the next response follows
+/// them on the connection.
+///
+/// When the server closes the connection before answering every request, the iteration stops and
+/// the requests left without a response are handed back by [`Pipeline::unanswered`], to be sent
+/// again on another connection. The server may have processed some of them already, which is why
+/// only idempotent requests should be pipelined. Requests are kept until they are answered, hence
+/// their bodies must be [`Clone`].
+///
+/// # Example
+/// ```no_run
+/// use std::net::TcpStream;
+/// use touche::{client::Pipeline, HttpBody};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let conn = TcpStream::connect("internal-service:80")?;
+///
+/// let mut pipeline = Pipeline::new(conn, 8);
+/// for id in 0..100 {
+///     pipeline.push(
+///         http::Request::builder()
+///             .uri(format!("/items/{id}"))
+///             .header("host", "internal-service")
+///             .body(())?,
+///     );
+/// }
+///
+/// for res in pipeline.by_ref() {
+///     println!("{}", String::from_utf8(res?.into_body().into_bytes()?)?);
+/// }
+///
+/// // Requests the server closed the connection on, to send again
+/// let unanswered = pipeline.unanswered();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Pipeline<B> {
+    reader: Arc<Mutex<Option<BufReader<Connection>>>>,
+    writer: BufWriter<Connection>,
+    max_depth: usize,
+    queued: VecDeque<http::Request<B>>,
+    in_flight: VecDeque<http::Request<B>>,
+    writable: bool,
+    closed: bool,
+}
+
+impl<B: HttpBody + Clone> Pipeline<B> {
+    /// Creates a pipeline on the connection, keeping at most `max_depth` requests in flight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_depth` is zero.
+    pub fn new(connection: impl Into<Connection>, max_depth: usize) -> Self {
+        assert!(max_depth > 0, "pipelines need a depth of at least 1");
+
+        let conn = connection.into();
+        Self {
+            reader: Arc::new(Mutex::new(Some(BufReader::new(conn.clone())))),
+            writer: BufWriter::new(conn),
+            max_depth,
+            queued: VecDeque::new(),
+            in_flight: VecDeque::new(),
+            writable: true,
+            closed: false,
+        }
+    }
+
+    /// Queues a request, written once there is room in the pipeline.
+    pub fn push(&mut self, req: http::Request<B>) {
+        self.queued.push_back(req);
+    }
+
+    /// Whether the connection is closed, either by the server or after a failure.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Takes the requests left without a response once the connection is closed, in order,
+    /// including the queued ones that were never written.
+    ///
+    /// Returns nothing while the connection is open.
+    pub fn unanswered(&mut self) -> Vec<http::Request<B>> {
+        match self.closed {
+            true => self
+                .in_flight
+                .drain(..)
+                .chain(self.queued.drain(..))
+                .collect(),
+            false => Vec::new(),
+        }
+    }
+
+    /// Writes queued requests until the pipeline is full.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.in_flight.len() < self.max_depth {
+            let Some(req) = self.queued.pop_front() else {
+                break;
+            };
+
+            let written = request::write_request_head(copy(&req), &mut self.writer).and_then(
+                |(encoding, body)| request::write_request_body(encoding, body, &mut self.writer),
+            );
+
+            if let Err(err) = written {
+                self.queued.push_front(req);
+                return Err(err);
+            }
+
+            self.in_flight.push_back(req);
+        }
+
+        self.writer.flush()
+    }
+
+    /// Reads the next response, along with its whole body.
+    fn read(&mut self, method: &Method) -> io::Result<http::Response<Body>> {
+        let reader = lock(&self.reader)
+            .take()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+
+        let reader = Lent {
+            reader: Some(reader),
+            home: self.reader.clone(),
+        };
+
+        let (parts, body) = response::parse_response(reader, method)
+            .map_err(into_io)?
+            .into_parts();
+
+        Ok(http::Response::from_parts(parts, body.into_bytes()?.into()))
+    }
+}
+
+impl<B: HttpBody + Clone> Iterator for Pipeline<B> {
+    type Item = io::Result<http::Response<Body>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.closed {
+            return None;
+        }
+
+        if self.writable {
+            if let Err(err) = self.fill() {
+                // The responses to the requests already written may still be read
+                if !is_closed(&err) {
+                    self.closed = true;
+                    return Some(Err(err));
+                }
+                self.writable = false;
+            }
+        }
+
+        let Some(method) = self.in_flight.front().map(|req| req.method().clone()) else {
+            self.closed = !self.writable;
+            return None;
+        };
+
+        match self.read(&method) {
+            Ok(res) => {
+                self.in_flight.pop_front();
+                self.closed = closes_connection(&method, &res)
+                    || res.status() == StatusCode::SWITCHING_PROTOCOLS;
+                Some(Ok(res))
+            }
+            Err(err) if is_closed(&err) => {
+                self.closed = true;
+                None
+            }
+            Err(err) => {
+                self.closed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A copy of the request to write, since requests are kept until they are answered.
+fn copy<B: Clone>(req: &http::Request<B>) -> http::Request<B> {
+    let mut copy = http::Request::new(req.body().clone());
+    *copy.method_mut() = req.method().clone();
+    *copy.uri_mut() = req.uri().clone();
+    *copy.version_mut() = req.version();
+    *copy.headers_mut() = req.headers().clone();
+    copy
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Lends the reader of the connection to a response body, which gives it back once dropped.
+struct Lent {
+    reader: Option<BufReader<Connection>>,
+    home: Arc<Mutex<Option<BufReader<Connection>>>>,
+}
+
+impl Lent {
+    fn reader(&mut self) -> &mut BufReader<Connection> {
+        self.reader.as_mut().expect("reader is only taken on drop")
+    }
+}
+
+impl Read for Lent {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader().read(buf)
+    }
+}
+
+impl BufRead for Lent {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader().fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader().consume(amt)
+    }
+}
+
+impl Drop for Lent {
+    fn drop(&mut self) {
+        *lock(&self.home) = self.reader.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::*;
+    use crate::Server;
+
+    fn request(path: &str) -> http::Request<&'static str> {
+        http::Request::builder().uri(path).body("").unwrap()
+    }
+
+    #[test]
+    fn pipelines_requests_on_a_single_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            Server::builder()
+                .max_threads(16)
+                .from_listener(listener)
+                .unwrap()
+                .serve(|req: http::Request<Body>| {
+                    let chunked = req.uri().path() == "/chunked";
+                    let body = match chunked {
+                        true => Body::from_iter([req.uri().to_string()]),
+                        false => Body::from(req.uri().to_string()),
+                    };
+                    http::Response::builder().body(body)
+                })
+                .ok()
+        });
+
+        let conn = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut pipeline = Pipeline::new(conn, 3);
+        for path in ["/1", "/2", "/chunked", "/4", "/5"] {
+            pipeline.push(request(path));
+        }
+
+        let bodies = pipeline
+            .by_ref()
+            .map(|res| res.unwrap().into_body().into_bytes().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            bodies,
+            [&b"/1"[..], b"/2", b"/chunked", b"/4", b"/5"].map(|body| body.to_vec())
+        );
+        assert!(!pipeline.is_closed());
+        assert!(pipeline.unanswered().is_empty());
+    }
+
+    #[test]
+    fn hands_back_unanswered_requests_when_the_server_closes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+
+            // Answers the first two requests only
+            for _ in 0..2 {
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                writer
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .unwrap();
+            }
+        });
+
+        let conn = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut pipeline = Pipeline::new(conn, 2);
+        for path in ["/1", "/2", "/3", "/4"] {
+            pipeline.push(request(path));
+        }
+
+        let responses = pipeline.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert!(pipeline.is_closed());
+
+        let unanswered = pipeline.unanswered();
+        assert_eq!(
+            unanswered.iter().map(|req| req.uri()).collect::<Vec<_>>(),
+            ["/3", "/4"]
+        );
+    }
+}
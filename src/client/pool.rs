@@ -0,0 +1,429 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use http::uri::{Authority, Scheme};
+
+use crate::Connection;
+
+/// Connections are pooled by origin, and by the Unix socket they were opened to, if any.
+pub(crate) type PoolKey = (Scheme, Authority, Option<PathBuf>);
+
+#[derive(Debug, Clone)]
+pub(crate) struct PoolConfig {
+    pub(crate) max_per_host: Option<usize>,
+    pub(crate) max_total: Option<usize>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) max_lifetime: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_per_host: None,
+            max_total: None,
+            idle_timeout: Some(Duration::from_secs(90)),
+            max_lifetime: None,
+        }
+    }
+}
+
+/// Keeps track of every open connection of a client, so they can be reused and limited.
+#[derive(Debug, Clone)]
+pub(crate) struct Pool(Arc<PoolInner>);
+
+#[derive(Debug)]
+struct PoolInner {
+    config: PoolConfig,
+    state: Mutex<PoolState>,
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    idle: HashMap<PoolKey, Vec<Idle>>,
+    open: HashMap<PoolKey, usize>,
+    total: usize,
+}
+
+#[derive(Debug)]
+struct Idle {
+    conn: Connection,
+    created_at: Instant,
+    idle_since: Instant,
+}
+
+impl Pool {
+    pub(crate) fn new(config: PoolConfig) -> Self {
+        Pool(Arc::new(PoolInner {
+            config,
+            state: Default::default(),
+            released: Condvar::new(),
+        }))
+    }
+
+    /// Checks out an idle connection to the given host, or reserves a slot to open a new one.
+    /// Blocks while the connection limits are reached, and returns `None` if the deadline passes
+    /// while doing so.
+    pub(crate) fn checkout(&self, key: &PoolKey, deadline: Option<Instant>) -> Option<Pooled> {
+        let mut state = self.lock();
+
+        loop {
+            self.evict_expired(&mut state);
+
+            while let Some(idle) = state.idle.get_mut(key).and_then(|idle| idle.pop()) {
+                if idle.conn.is_stale() {
+                    state.close(key);
+                    continue;
+                }
+
+                return Some(Pooled {
+                    pool: self.clone(),
+                    key: key.clone(),
+                    created_at: idle.created_at,
+                    conn: Some(idle.conn),
+                    reused: true,
+                    returned: false,
+                });
+            }
+
+            let host_full = self
+                .0
+                .config
+                .max_per_host
+                .filter(|max| state.open.get(key).copied().unwrap_or(0) >= *max)
+                .is_some();
+
+            let total_full = self
+                .0
+                .config
+                .max_total
+                .filter(|max| state.total >= *max)
+                .is_some();
+
+            if !host_full && total_full && state.close_oldest_idle() {
+                continue;
+            }
+
+            if !host_full && !total_full {
+                *state.open.entry(key.clone()).or_default() += 1;
+                state.total += 1;
+
+                return Some(Pooled {
+                    pool: self.clone(),
+                    key: key.clone(),
+                    created_at: Instant::now(),
+                    conn: None,
+                    reused: false,
+                    returned: false,
+                });
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return None;
+                    }
+                    let (state, _) = self
+                        .0
+                        .released
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(|err| err.into_inner());
+                    state
+                }
+                None => self
+                    .0
+                    .released
+                    .wait(state)
+                    .unwrap_or_else(|err| err.into_inner()),
+            };
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.0.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn evict_expired(&self, state: &mut PoolState) {
+        let config = &self.0.config;
+        let now = Instant::now();
+
+        let mut expired = Vec::new();
+
+        for (key, idle) in state.idle.iter_mut() {
+            idle.retain(|idle| {
+                let idle_expired = config
+                    .idle_timeout
+                    .filter(|timeout| now.duration_since(idle.idle_since) >= *timeout)
+                    .is_some();
+
+                let lifetime_expired = config
+                    .max_lifetime
+                    .filter(|lifetime| now.duration_since(idle.created_at) >= *lifetime)
+                    .is_some();
+
+                if idle_expired || lifetime_expired {
+                    expired.push(key.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        for key in expired {
+            state.close(&key);
+        }
+    }
+}
+
+impl PoolState {
+    fn close(&mut self, key: &PoolKey) {
+        if let Some(open) = self.open.get_mut(key) {
+            *open -= 1;
+            if *open == 0 {
+                self.open.remove(key);
+                self.idle.remove(key);
+            }
+        }
+        self.total -= 1;
+    }
+
+    fn close_oldest_idle(&mut self) -> bool {
+        let oldest = self
+            .idle
+            .iter()
+            .filter_map(|(key, idle)| Some((key, idle.first()?.idle_since)))
+            .min_by_key(|(_key, idle_since)| *idle_since)
+            .map(|(key, _)| key.clone());
+
+        match oldest {
+            Some(key) => {
+                self.idle.get_mut(&key).unwrap().remove(0);
+                self.close(&key);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A slot on the [`Pool`], which may already hold a reused [`Connection`].
+///
+/// Connections given back with [`Pooled::release`] become idle, any other way of dropping this
+/// closes the connection and frees the slot.
+#[derive(Debug)]
+pub(crate) struct Pooled {
+    pool: Pool,
+    key: PoolKey,
+    created_at: Instant,
+    conn: Option<Connection>,
+    reused: bool,
+    returned: bool,
+}
+
+impl Pooled {
+    pub(crate) fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    pub(crate) fn take(&mut self) -> Option<Connection> {
+        self.conn.take()
+    }
+
+    /// Gives the connection back to the pool, so it can be reused by other requests.
+    pub(crate) fn release(mut self, conn: Connection) {
+        let expired = self
+            .pool
+            .0
+            .config
+            .max_lifetime
+            .filter(|lifetime| self.created_at.elapsed() >= *lifetime)
+            .is_some();
+
+        if expired {
+            return;
+        }
+
+        let mut state = self.pool.lock();
+        state.idle.entry(self.key.clone()).or_default().push(Idle {
+            conn,
+            created_at: self.created_at,
+            idle_since: Instant::now(),
+        });
+        drop(state);
+
+        // Keeps the slot taken, since the connection is now idle
+        self.returned = true;
+        self.pool.0.released.notify_all();
+    }
+}
+
+impl Drop for Pooled {
+    fn drop(&mut self) {
+        if self.returned {
+            return;
+        }
+
+        self.pool.lock().close(&self.key);
+        self.pool.0.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::*;
+
+    fn key(authority: &'static str) -> PoolKey {
+        (Scheme::HTTP, Authority::from_static(authority), None)
+    }
+
+    fn connection(listener: &TcpListener) -> (Connection, TcpStream) {
+        let conn = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (conn.into(), server)
+    }
+
+    #[test]
+    fn reuses_released_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(PoolConfig::default());
+
+        let mut pooled = pool.checkout(&key("a.com"), None).unwrap();
+        assert!(!pooled.is_reused());
+        assert!(pooled.take().is_none());
+
+        let (conn, _server) = connection(&listener);
+        let addr = conn.local_addr();
+        pooled.release(conn);
+
+        let mut pooled = pool.checkout(&key("a.com"), None).unwrap();
+        assert!(pooled.is_reused());
+        assert_eq!(pooled.take().unwrap().local_addr(), addr);
+
+        let mut pooled = pool.checkout(&key("b.com"), None).unwrap();
+        assert!(!pooled.is_reused());
+        assert!(pooled.take().is_none());
+    }
+
+    #[test]
+    fn discards_stale_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(PoolConfig::default());
+
+        let (conn, server) = connection(&listener);
+        pool.checkout(&key("a.com"), None).unwrap().release(conn);
+        drop(server);
+
+        let (conn, mut server) = connection(&listener);
+        pool.checkout(&key("b.com"), None).unwrap().release(conn);
+        server.write_all(b"unexpected").unwrap();
+
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(!pool.checkout(&key("a.com"), None).unwrap().is_reused());
+        assert!(!pool.checkout(&key("b.com"), None).unwrap().is_reused());
+        assert_eq!(pool.lock().total, 0);
+    }
+
+    #[test]
+    fn expires_idle_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(PoolConfig {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+
+        let (conn, _server) = connection(&listener);
+        pool.checkout(&key("a.com"), None).unwrap().release(conn);
+        assert!(pool.checkout(&key("a.com"), None).unwrap().is_reused());
+
+        let (conn, _server) = connection(&listener);
+        pool.checkout(&key("a.com"), None).unwrap().release(conn);
+        thread::sleep(Duration::from_millis(60));
+        assert!(!pool.checkout(&key("a.com"), None).unwrap().is_reused());
+    }
+
+    #[test]
+    fn does_not_reuse_connections_older_than_their_max_lifetime() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(PoolConfig {
+            max_lifetime: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+
+        let pooled = pool.checkout(&key("a.com"), None).unwrap();
+        let (conn, _server) = connection(&listener);
+        thread::sleep(Duration::from_millis(60));
+        pooled.release(conn);
+
+        assert!(!pool.checkout(&key("a.com"), None).unwrap().is_reused());
+        assert_eq!(pool.lock().total, 0);
+    }
+
+    #[test]
+    fn waits_for_a_free_slot_when_the_host_limit_is_reached() {
+        let pool = Pool::new(PoolConfig {
+            max_per_host: Some(1),
+            ..Default::default()
+        });
+
+        let pooled = pool.checkout(&key("a.com"), None).unwrap();
+
+        let other_host = pool.checkout(&key("b.com"), None).unwrap();
+        assert!(!other_host.is_reused());
+
+        let waiting = thread::spawn({
+            let pool = pool.clone();
+            move || pool.checkout(&key("a.com"), None).unwrap().is_reused()
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+
+        drop(pooled);
+        assert!(!waiting.join().unwrap());
+    }
+
+    #[test]
+    fn gives_up_waiting_for_a_free_slot_after_the_deadline() {
+        let pool = Pool::new(PoolConfig {
+            max_total: Some(1),
+            ..Default::default()
+        });
+
+        let _pooled = pool.checkout(&key("a.com"), None).unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert!(pool.checkout(&key("b.com"), Some(deadline)).is_none());
+        assert!(Instant::now() >= deadline);
+    }
+
+    #[test]
+    fn closes_idle_connections_from_other_hosts_when_the_total_limit_is_reached() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(PoolConfig {
+            max_total: Some(2),
+            ..Default::default()
+        });
+
+        let (conn, _server) = connection(&listener);
+        pool.checkout(&key("a.com"), None).unwrap().release(conn);
+        let _b = pool.checkout(&key("b.com"), None).unwrap();
+
+        let c = pool.checkout(&key("c.com"), None).unwrap();
+        assert!(!c.is_reused());
+        assert_eq!(pool.lock().total, 2);
+        assert!(!pool.lock().idle.contains_key(&key("a.com")));
+    }
+}
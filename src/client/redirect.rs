@@ -0,0 +1,283 @@
+use http::{
+    header::LOCATION,
+    request::Parts,
+    uri::{PathAndQuery, Scheme},
+    Method, Response, StatusCode, Uri,
+};
+
+use super::RequestError;
+
+/// Decides which redirect responses the [`Client`](super::Client) follows.
+///
+/// Redirects with 301 and 302 statuses turn `POST` requests into `GET` ones, and 303 turns every
+/// method but `HEAD` into `GET`, dropping the body. 307 and 308 redirects keep both the method
+/// and the body, but are only followed if the body could be buffered to be sent again.
+///
+/// The `Authorization` and `Cookie` headers are never sent to other origins.
+///
+/// # Example
+/// ```no_run
+/// use touche::{client::RedirectPolicy, Client};
+///
+/// let client = Client::builder()
+///     .redirect(RedirectPolicy::limited(5).same_origin_only())
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RedirectPolicy {
+    max_redirects: usize,
+    same_origin_only: bool,
+}
+
+impl RedirectPolicy {
+    /// Never follows redirects, returning 3xx responses as they are. This is the default.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Follows up to `max_redirects` in a row. Requests redirected more than that fail with
+    /// [`RequestError::TooManyRedirects`].
+    pub fn limited(max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            ..Default::default()
+        }
+    }
+
+    /// Only follows redirects to the same origin (scheme, host and port) as the request. Responses
+    /// redirecting elsewhere are returned as they are.
+    pub fn same_origin_only(self) -> Self {
+        Self {
+            same_origin_only: true,
+            ..self
+        }
+    }
+
+    /// Returns where the request should go next, if the response is a redirect to be followed.
+    pub(crate) fn redirect<B>(
+        &self,
+        redirects: usize,
+        req: &Parts,
+        res: &Response<B>,
+    ) -> Result<Option<Redirect>, RequestError> {
+        if self.max_redirects == 0 {
+            return Ok(None);
+        }
+
+        let method = match res.status() {
+            StatusCode::SEE_OTHER if req.method != Method::HEAD => Method::GET,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND if req.method == Method::POST => {
+                Method::GET
+            }
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => req.method.clone(),
+            _ => return Ok(None),
+        };
+
+        let Some(uri) = res
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| resolve(&req.uri, location))
+        else {
+            return Ok(None);
+        };
+
+        let same_origin = is_same_origin(&req.uri, &uri);
+
+        if self.same_origin_only && !same_origin {
+            return Ok(None);
+        }
+
+        if redirects >= self.max_redirects {
+            return Err(RequestError::TooManyRedirects);
+        }
+
+        Ok(Some(Redirect {
+            keep_body: method == req.method,
+            uri,
+            method,
+            same_origin,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Redirect {
+    pub(crate) uri: Uri,
+    pub(crate) method: Method,
+    pub(crate) keep_body: bool,
+    pub(crate) same_origin: bool,
+}
+
+/// The URIs a request went through while following redirects, available as an extension of the
+/// final response.
+///
+/// It starts with the URI originally requested and ends with the one that answered the final
+/// response. Responses that were not redirected don't have this extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectChain(pub(crate) Vec<Uri>);
+
+impl RedirectChain {
+    /// All the requested URIs, in order.
+    pub fn uris(&self) -> &[Uri] {
+        &self.0
+    }
+}
+
+/// Resolves the `Location` of a redirect against the URI that was requested.
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    // Fragments are never sent to servers
+    let location = location.split('#').next()?;
+    let scheme = base.scheme().cloned().unwrap_or(Scheme::HTTP);
+
+    if location.starts_with("//") {
+        return format!("{scheme}:{location}").parse().ok();
+    }
+
+    if let Ok(uri) = location.parse::<Uri>() {
+        if uri.scheme().is_some() {
+            return Some(uri);
+        }
+    }
+
+    let path_and_query = if location.starts_with('/') {
+        location.to_string()
+    } else if location.starts_with('?') {
+        format!("{}{location}", base.path())
+    } else {
+        let dir = base.path().rsplit_once('/').map_or("", |(dir, _)| dir);
+        format!("{dir}/{location}")
+    };
+
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path_and_query.as_str(), None),
+    };
+
+    let path_and_query = match query {
+        Some(query) => format!("{}?{query}", remove_dot_segments(path)),
+        None => remove_dot_segments(path),
+    };
+
+    Uri::builder()
+        .scheme(scheme)
+        .authority(base.authority()?.clone())
+        .path_and_query(path_and_query.parse::<PathAndQuery>().ok()?)
+        .build()
+        .ok()
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut ends_in_dir = false;
+
+    for segment in path.split('/').skip(1) {
+        ends_in_dir = matches!(segment, "." | "..");
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    if ends_in_dir {
+        segments.push("");
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+fn is_same_origin(a: &Uri, b: &Uri) -> bool {
+    fn origin(uri: &Uri) -> Option<(Scheme, String, u16)> {
+        let scheme = uri.scheme().cloned().unwrap_or(Scheme::HTTP);
+        let authority = uri.authority()?;
+        let default_port = if scheme == Scheme::HTTPS { 443 } else { 80 };
+        let port = authority.port_u16().unwrap_or(default_port);
+        Some((scheme, authority.host().to_ascii_lowercase(), port))
+    }
+
+    matches!((origin(a), origin(b)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_locations() {
+        let base: Uri = "http://example.com/a/b?c=d".parse().unwrap();
+
+        let cases = [
+            ("https://other.com/x", "https://other.com/x"),
+            ("//other.com/x", "http://other.com/x"),
+            ("/x?y=z", "http://example.com/x?y=z"),
+            ("x", "http://example.com/a/x"),
+            ("../x", "http://example.com/x"),
+            ("./x/../y", "http://example.com/a/y"),
+            ("?e=f", "http://example.com/a/b?e=f"),
+            ("/x#fragment", "http://example.com/x"),
+        ];
+
+        for (location, expected) in cases {
+            assert_eq!(resolve(&base, location).unwrap(), expected, "{location}");
+        }
+    }
+
+    #[test]
+    fn compares_origins() {
+        let uri = |uri: &str| uri.parse::<Uri>().unwrap();
+
+        assert!(is_same_origin(
+            &uri("http://example.com/a"),
+            &uri("http://EXAMPLE.com:80/b")
+        ));
+        assert!(!is_same_origin(
+            &uri("http://example.com/a"),
+            &uri("https://example.com/a")
+        ));
+        assert!(!is_same_origin(
+            &uri("http://example.com/a"),
+            &uri("http://example.com:8080/a")
+        ));
+    }
+
+    #[test]
+    fn rewrites_methods() {
+        let policy = RedirectPolicy::limited(1);
+
+        let cases = [
+            (Method::POST, 301, Method::GET, false),
+            (Method::PUT, 301, Method::PUT, true),
+            (Method::POST, 302, Method::GET, false),
+            (Method::DELETE, 303, Method::GET, false),
+            (Method::HEAD, 303, Method::HEAD, true),
+            (Method::POST, 307, Method::POST, true),
+            (Method::POST, 308, Method::POST, true),
+        ];
+
+        for (method, status, expected, keep_body) in cases {
+            let (req, _) = http::Request::builder()
+                .method(method.clone())
+                .uri("http://example.com/")
+                .body(())
+                .unwrap()
+                .into_parts();
+
+            let res = Response::builder()
+                .status(status)
+                .header(LOCATION, "/next")
+                .body(())
+                .unwrap();
+
+            let redirect = policy.redirect(0, &req, &res).unwrap().unwrap();
+            assert_eq!(redirect.method, expected, "{method} {status}");
+            assert_eq!(redirect.keep_body, keep_body, "{method} {status}");
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::io::Read;
+
+use http::{header::CONTENT_TYPE, Response};
+
+use super::RequestError;
+use crate::{Body, HttpBody};
+
+/// Helpers to read the responses of a [`Client`](super::Client).
+pub trait ResponseExt: Sized {
+    /// Reads the body as text, decoded with the charset of the `Content-Type` header.
+    ///
+    /// UTF-8 is assumed when there is no charset, and `ISO-8859-1`, `windows-1252` and
+    /// `US-ASCII` are decoded as well. Invalid or unsupported text is decoded as UTF-8, with its
+    /// invalid sequences replaced by `U+FFFD`.
+    fn text(self) -> Result<String, RequestError>;
+
+    /// Deserializes the body as JSON.
+    #[cfg(feature = "json")]
+    fn json<T: serde::de::DeserializeOwned>(self) -> Result<T, RequestError>;
+
+    /// Reads the body, failing with [`RequestError::BodyTooLarge`] if it is longer than `max`
+    /// bytes. Bodies are only read up to that point, so servers can't exhaust the memory.
+    fn bytes_limited(self, max: u64) -> Result<Vec<u8>, RequestError>;
+
+    /// Fails with [`RequestError::Status`] if the status is a client or server error.
+    fn error_for_status(self) -> Result<Self, RequestError>;
+}
+
+impl ResponseExt for Response<Body> {
+    fn text(self) -> Result<String, RequestError> {
+        let charset = self
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(charset)
+            .map(|charset| charset.to_ascii_lowercase());
+
+        let bytes = self.into_body().into_bytes()?;
+
+        Ok(match charset.as_deref() {
+            Some("iso-8859-1" | "latin1" | "us-ascii" | "ascii") => {
+                bytes.iter().map(|&byte| byte as char).collect()
+            }
+            Some("windows-1252" | "cp1252") => {
+                bytes.iter().map(|&byte| windows_1252(byte)).collect()
+            }
+            _ => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+            },
+        })
+    }
+
+    #[cfg(feature = "json")]
+    fn json<T: serde::de::DeserializeOwned>(self) -> Result<T, RequestError> {
+        serde_json::from_reader(self.into_body().into_reader()).map_err(RequestError::Json)
+    }
+
+    fn bytes_limited(self, max: u64) -> Result<Vec<u8>, RequestError> {
+        let body = self.into_body();
+
+        if body.len().is_some_and(|len| len > max) {
+            return Err(RequestError::BodyTooLarge(max));
+        }
+
+        let mut bytes = Vec::new();
+        body.into_reader()
+            .take(max.saturating_add(1))
+            .read_to_end(&mut bytes)?;
+
+        match bytes.len() as u64 > max {
+            true => Err(RequestError::BodyTooLarge(max)),
+            false => Ok(bytes),
+        }
+    }
+
+    fn error_for_status(self) -> Result<Self, RequestError> {
+        let status = self.status();
+
+        match status.is_client_error() || status.is_server_error() {
+            true => Err(RequestError::Status(status)),
+            false => Ok(self),
+        }
+    }
+}
+
+/// The `charset` parameter of a `Content-Type`.
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Decodes a `windows-1252` byte, which only differs from `ISO-8859-1` between 0x80 and 0x9f.
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+
+    match byte {
+        0x80..=0x9f => HIGH[(byte - 0x80) as usize],
+        byte => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    fn response(content_type: &str, body: impl Into<Body>) -> Response<Body> {
+        Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_text_with_its_charset() {
+        let res = response("text/plain; charset=utf-8", "touché");
+        assert_eq!(res.text().unwrap(), "touché");
+
+        let res = response("text/plain; charset=\"ISO-8859-1\"", &b"touch\xe9"[..]);
+        assert_eq!(res.text().unwrap(), "touché");
+
+        let res = response("text/plain;charset=windows-1252", &b"\x80 \x93ok\x94"[..]);
+        assert_eq!(res.text().unwrap(), "€ “ok”");
+
+        let res = response("text/plain", &b"bad \xff"[..]);
+        assert_eq!(res.text().unwrap(), "bad \u{fffd}");
+    }
+
+    #[test]
+    fn limits_body_sizes() {
+        let res = response("text/plain", "Hello world");
+        assert_eq!(res.bytes_limited(11).unwrap(), b"Hello world");
+
+        let res = response("text/plain", "Hello world");
+        assert!(matches!(
+            res.bytes_limited(10),
+            Err(RequestError::BodyTooLarge(10))
+        ));
+
+        let res = response("text/plain", Body::from_iter(["Hello", " world"]));
+        assert!(matches!(
+            res.bytes_limited(5),
+            Err(RequestError::BodyTooLarge(5))
+        ));
+    }
+
+    #[test]
+    fn fails_for_error_statuses() {
+        let res = |status| {
+            Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        assert!(res(StatusCode::OK).error_for_status().is_ok());
+        assert!(res(StatusCode::NOT_MODIFIED).error_for_status().is_ok());
+        assert!(matches!(
+            res(StatusCode::NOT_FOUND).error_for_status(),
+            Err(RequestError::Status(StatusCode::NOT_FOUND))
+        ));
+        assert!(matches!(
+            res(StatusCode::BAD_GATEWAY).error_for_status(),
+            Err(RequestError::Status(StatusCode::BAD_GATEWAY))
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn deserializes_json() {
+        let res = response("application/json", r#"{"name":"touché","tags":["http"]}"#);
+        let value: serde_json::Value = res.json().unwrap();
+        assert_eq!(value["name"], "touché");
+
+        let res = response("application/json", "{");
+        assert!(matches!(
+            res.json::<serde_json::Value>(),
+            Err(RequestError::Json(_))
+        ));
+    }
+}
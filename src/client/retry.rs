@@ -0,0 +1,286 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime},
+};
+
+use headers::Header;
+use http::{header::RETRY_AFTER, Method, Response, StatusCode};
+
+use super::{is_closed, RequestError};
+
+/// Decides which failed requests the [`Client`](super::Client) sends again.
+///
+/// Requests are retried when they fail to connect, when the connection is lost or times out
+/// before the response arrives, or when the response has one of the retried statuses, which by
+/// default are 429, 502, 503 and 504. Only requests with idempotent methods are retried once they
+/// may have reached the server. Others are only retried when the connection could not even be
+/// opened.
+///
+/// Retries wait for an exponential backoff with jitter, or for as long as the `Retry-After` header
+/// of the response asks. Requests that would wait longer than the maximum delay, or past the
+/// deadline set with [`ClientBuilder::timeout`](super::ClientBuilder::timeout), are not retried.
+/// Bodies must be small enough to be buffered for their requests to be retried.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+/// use touche::{client::RetryPolicy, Client};
+///
+/// let client = Client::builder()
+///     .retry(
+///         RetryPolicy::limited(3)
+///             .backoff(Duration::from_millis(200), Duration::from_secs(5))
+///             .statuses([http::StatusCode::SERVICE_UNAVAILABLE]),
+///     )
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    methods: Vec<Method>,
+    statuses: Vec<StatusCode>,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            methods: vec![
+                Method::GET,
+                Method::HEAD,
+                Method::OPTIONS,
+                Method::TRACE,
+                Method::PUT,
+                Method::DELETE,
+            ],
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries requests. This is the default.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Retries each request up to `max_retries` times.
+    pub fn limited(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the methods that are safe to send again once a request may have reached the server.
+    /// Defaults to the idempotent ones: `GET`, `HEAD`, `OPTIONS`, `TRACE`, `PUT` and `DELETE`.
+    ///
+    /// Requests to these methods are also sent again when a kept alive connection turns out to
+    /// be closed, even without retries.
+    pub fn methods(self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Self {
+            methods: methods.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the response statuses that are retried. Defaults to 429, 502, 503 and 504.
+    pub fn statuses(self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        Self {
+            statuses: statuses.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Waits `base` before the first retry, doubling the wait on each one after it up to `max`.
+    /// Waits are randomly shortened by up to half, so that clients don't retry all at once.
+    /// Defaults to 100 milliseconds and 10 seconds.
+    pub fn backoff(self, base: Duration, max: Duration) -> Self {
+        Self {
+            base_delay: base,
+            max_delay: max,
+            ..self
+        }
+    }
+
+    /// Whether requests with the method may be sent again after reaching the server.
+    pub(crate) fn is_idempotent(&self, method: &Method) -> bool {
+        self.methods.contains(method)
+    }
+
+    /// Returns how long to wait before retrying a request that failed with `err`, if it should be
+    /// retried at all. Requests that were not `sent` failed before reaching the server.
+    pub(crate) fn retry_error(
+        &self,
+        retries: usize,
+        method: &Method,
+        err: &RequestError,
+        sent: bool,
+    ) -> Option<Duration> {
+        if retries >= self.max_retries || (sent && !self.is_idempotent(method)) {
+            return None;
+        }
+
+        let transient = match err {
+            RequestError::ConnectTimeout | RequestError::ReadTimeout => true,
+            RequestError::Io(err) => !sent || is_closed(err),
+            _ => false,
+        };
+
+        transient.then(|| self.backoff_delay(retries))
+    }
+
+    /// Returns how long to wait before retrying a request answered with `res`, if it should be
+    /// retried at all.
+    pub(crate) fn retry_response<B>(
+        &self,
+        retries: usize,
+        method: &Method,
+        res: &Response<B>,
+    ) -> Option<Duration> {
+        if retries >= self.max_retries
+            || !self.is_idempotent(method)
+            || !self.statuses.contains(&res.status())
+        {
+            return None;
+        }
+
+        match retry_after(res) {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff_delay(retries)),
+        }
+    }
+
+    fn backoff_delay(&self, retries: usize) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << retries.min(31))
+            .min(self.max_delay);
+
+        delay / 2 + delay.mul_f64(jitter() / 2.0)
+    }
+}
+
+/// How many times the request that answered a response was sent, available as an extension of
+/// the responses of a [`Client`](super::Client).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempts(pub(crate) usize);
+
+impl Attempts {
+    /// The number of attempts, which is 1 for requests that were not retried.
+    pub fn count(&self) -> usize {
+        self.0
+    }
+}
+
+/// The delay asked by the `Retry-After` header, given either in seconds or as a date.
+fn retry_after<B>(res: &Response<B>) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?;
+
+    if let Some(seconds) = value.to_str().ok().and_then(|value| value.parse().ok()) {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = headers::Date::decode(&mut std::iter::once(value)).ok()?;
+    Some(
+        SystemTime::from(date)
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// A random number between 0 and 1.
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn retries_only_idempotent_requests_once_sent() {
+        let policy = RetryPolicy::limited(2);
+        let reset = || RequestError::Io(io::ErrorKind::ConnectionReset.into());
+
+        assert!(policy
+            .retry_error(0, &Method::GET, &reset(), true)
+            .is_some());
+        assert!(policy
+            .retry_error(1, &Method::PUT, &RequestError::ReadTimeout, true)
+            .is_some());
+        assert!(policy
+            .retry_error(2, &Method::GET, &reset(), true)
+            .is_none());
+        assert!(policy
+            .retry_error(0, &Method::POST, &reset(), true)
+            .is_none());
+        assert!(policy
+            .retry_error(0, &Method::POST, &RequestError::ConnectTimeout, false)
+            .is_some());
+        assert!(policy
+            .retry_error(0, &Method::GET, &RequestError::Timeout, true)
+            .is_none());
+
+        assert!(RetryPolicy::none()
+            .retry_error(0, &Method::GET, &reset(), false)
+            .is_none());
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy::limited(10)
+            .backoff(Duration::from_millis(100), Duration::from_millis(300));
+
+        for (retries, max) in [(0, 100), (1, 200), (2, 300), (5, 300)] {
+            let delay = policy.backoff_delay(retries);
+            assert!(delay >= Duration::from_millis(max / 2));
+            assert!(delay <= Duration::from_millis(max));
+        }
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let policy = RetryPolicy::limited(1).backoff(Duration::ZERO, Duration::from_secs(30));
+        let res = |status: u16, retry_after: &str| {
+            Response::builder()
+                .status(status)
+                .header(RETRY_AFTER, retry_after)
+                .body(())
+                .unwrap()
+        };
+
+        assert_eq!(
+            policy.retry_response(0, &Method::GET, &res(503, "20")),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            policy.retry_response(0, &Method::GET, &res(503, "Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            policy.retry_response(0, &Method::GET, &res(429, "60")),
+            None
+        );
+        assert_eq!(policy.retry_response(0, &Method::GET, &res(500, "1")), None);
+        assert_eq!(
+            policy.retry_response(0, &Method::POST, &res(503, "1")),
+            None
+        );
+    }
+}
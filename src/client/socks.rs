@@ -0,0 +1,248 @@
+use std::{
+    io::{self, Read, Write},
+    net::IpAddr,
+};
+
+const VERSION: u8 = 5;
+const NO_AUTH: u8 = 0;
+const USERNAME_PASSWORD: u8 = 2;
+const NO_ACCEPTABLE_METHODS: u8 = 0xff;
+const CONNECT: u8 = 1;
+const IPV4: u8 = 1;
+const DOMAIN: u8 = 3;
+const IPV6: u8 = 4;
+
+/// Asks a SOCKS5 proxy to connect to `host` and `port`, after which `stream` is connected to it.
+///
+/// IP addresses are sent as such, and host names are sent to be resolved by the proxy.
+pub(crate) fn connect<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> io::Result<()> {
+    let method = match credentials {
+        Some(_) => USERNAME_PASSWORD,
+        None => NO_AUTH,
+    };
+    stream.write_all(&[VERSION, 1, method])?;
+    stream.flush()?;
+
+    let mut choice = [0; 2];
+    stream.read_exact(&mut choice)?;
+
+    match choice {
+        [VERSION, NO_AUTH] => {}
+        [VERSION, USERNAME_PASSWORD] => {
+            let (username, password) = credentials.unwrap_or_default();
+            authenticate(stream, username, password)?;
+        }
+        [VERSION, NO_ACCEPTABLE_METHODS] => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "socks proxy requires authentication",
+            ))
+        }
+        _ => return Err(invalid_data("invalid socks proxy handshake")),
+    }
+
+    let mut req = vec![VERSION, CONNECT, 0];
+    encode_address(&mut req, host, port)?;
+    stream.write_all(&req)?;
+    stream.flush()?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply)?;
+
+    match reply {
+        [VERSION, 0, _, _] => {}
+        [VERSION, code, _, _] => {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("socks proxy refused to connect: {}", reason(code)),
+            ))
+        }
+        _ => return Err(invalid_data("invalid socks proxy reply")),
+    }
+
+    // The address the proxy bound to is of no use
+    let bound = match reply[3] {
+        IPV4 => 4,
+        IPV6 => 16,
+        DOMAIN => {
+            let mut len = [0; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as u64
+        }
+        _ => return Err(invalid_data("invalid socks proxy reply")),
+    };
+    io::copy(&mut stream.take(bound + 2), &mut io::sink())?;
+
+    Ok(())
+}
+
+fn authenticate<S: Read + Write>(stream: &mut S, username: &str, password: &str) -> io::Result<()> {
+    let (Ok(username_len), Ok(password_len)) =
+        (u8::try_from(username.len()), u8::try_from(password.len()))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "socks credentials are too long",
+        ));
+    };
+
+    let mut req = vec![1, username_len];
+    req.extend_from_slice(username.as_bytes());
+    req.push(password_len);
+    req.extend_from_slice(password.as_bytes());
+    stream.write_all(&req)?;
+    stream.flush()?;
+
+    let mut status = [0; 2];
+    stream.read_exact(&mut status)?;
+
+    match status {
+        [1, 0] => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "socks proxy authentication failed",
+        )),
+    }
+}
+
+fn encode_address(buf: &mut Vec<u8>, host: &str, port: u16) -> io::Result<()> {
+    // IPv6 hosts are bracketed on URIs
+    let host = host.trim_matches(['[', ']']);
+
+    let Ok(ip) = host.parse::<IpAddr>() else {
+        let len = u8::try_from(host.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "host name is too long"))?;
+        buf.extend_from_slice(&[DOMAIN, len]);
+        buf.extend_from_slice(host.as_bytes());
+        buf.extend_from_slice(&port.to_be_bytes());
+        return Ok(());
+    };
+
+    match ip {
+        IpAddr::V4(ip) => {
+            buf.push(IPV4);
+            buf.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            buf.push(IPV6);
+            buf.extend_from_slice(&ip.octets());
+        }
+    }
+    buf.extend_from_slice(&port.to_be_bytes());
+
+    Ok(())
+}
+
+fn reason(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Replies with scripted bytes, recording everything written to it.
+    struct Scripted {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Scripted {
+        fn new(replies: &[u8]) -> Self {
+            Self {
+                replies: Cursor::new(replies.to_vec()),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Scripted {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Scripted {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn connects_with_credentials_and_remote_dns() {
+        let mut stream = Scripted::new(&[5, 2, 1, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0, 80]);
+
+        connect(&mut stream, "example.com", 443, Some(("user", "pass"))).unwrap();
+
+        assert_eq!(
+            stream.written,
+            [
+                &[5, 1, 2][..],
+                &[1, 4],
+                b"user",
+                &[4],
+                b"pass",
+                &[5, 1, 0, 3, 11],
+                b"example.com",
+                &[1, 187],
+            ]
+            .concat()
+        );
+        assert_eq!(stream.replies.position(), 14);
+    }
+
+    #[test]
+    fn sends_ip_addresses() {
+        let mut stream = Scripted::new(&[5, 0, 5, 0, 0, 3, 3, b'a', b'.', b'b', 0, 80]);
+        connect(&mut stream, "[::1]", 8080, None).unwrap();
+
+        let mut expected = vec![5, 1, 0, 5, 1, 0, 4];
+        expected.extend_from_slice(&[0; 15]);
+        expected.extend_from_slice(&[1, 0x1f, 0x90]);
+        assert_eq!(stream.written, expected);
+
+        let mut stream = Scripted::new(&[5, 0, 5, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        connect(&mut stream, "10.0.0.1", 80, None).unwrap();
+        assert_eq!(stream.written[6..], [IPV4, 10, 0, 0, 1, 0, 80]);
+    }
+
+    #[test]
+    fn reports_refused_connections() {
+        let mut stream = Scripted::new(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let err = connect(&mut stream, "example.com", 80, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+        let mut stream = Scripted::new(&[5, 2, 1, 1]);
+        let err = connect(&mut stream, "example.com", 80, Some(("a", "b"))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        let mut stream = Scripted::new(&[5, 0xff]);
+        let err = connect(&mut stream, "example.com", 80, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use crate::Connection;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: Option<Duration>,
+    pub(crate) read: Option<Duration>,
+    pub(crate) write: Option<Duration>,
+    pub(crate) total: Option<Duration>,
+}
+
+impl Timeouts {
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.total.map(|total| Instant::now() + total)
+    }
+}
+
+/// The timeout that expired. Carried as the inner error of [`io::ErrorKind::TimedOut`] errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Elapsed {
+    Connect,
+    Read,
+    Write,
+    Deadline,
+}
+
+impl Elapsed {
+    pub(crate) fn find(err: &io::Error) -> Option<Self> {
+        err.get_ref()?.downcast_ref().copied()
+    }
+
+    /// Replaces errors caused by timed out socket operations.
+    pub(crate) fn or(self, err: io::Error) -> io::Error {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => self.into(),
+            _ => err,
+        }
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Elapsed::Connect => f.write_str("connect timed out"),
+            Elapsed::Read => f.write_str("read timed out"),
+            Elapsed::Write => f.write_str("write timed out"),
+            Elapsed::Deadline => f.write_str("request deadline exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+impl From<Elapsed> for io::Error {
+    fn from(elapsed: Elapsed) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, elapsed)
+    }
+}
+
+/// Returns the timeout to wait for, which is the closest of `timeout` and the `deadline`.
+pub(crate) fn limit(
+    timeout: Option<Duration>,
+    elapsed: Elapsed,
+    deadline: Option<Instant>,
+) -> io::Result<(Option<Duration>, Elapsed)> {
+    let Some(deadline) = deadline else {
+        return Ok((timeout, elapsed));
+    };
+
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    if remaining.is_zero() {
+        return Err(Elapsed::Deadline.into());
+    }
+
+    match timeout {
+        Some(timeout) if timeout < remaining => Ok((Some(timeout), elapsed)),
+        _ => Ok((Some(remaining), Elapsed::Deadline)),
+    }
+}
+
+/// A [`Connection`] which applies the per operation timeouts and the request deadline.
+#[derive(Clone)]
+pub(crate) struct TimedConnection {
+    conn: Connection,
+    timeouts: Timeouts,
+    deadline: Option<Instant>,
+    connecting: bool,
+    wait: Option<Duration>,
+}
+
+impl TimedConnection {
+    pub(crate) fn new(conn: Connection, timeouts: Timeouts, deadline: Option<Instant>) -> Self {
+        Self {
+            conn,
+            timeouts,
+            deadline,
+            connecting: false,
+            wait: None,
+        }
+    }
+
+    /// For handshakes made while connecting, such as with proxies, which are limited by the
+    /// connect timeout instead.
+    pub(crate) fn connecting(
+        conn: Connection,
+        timeouts: Timeouts,
+        deadline: Option<Instant>,
+    ) -> Self {
+        Self {
+            connecting: true,
+            ..Self::new(conn, timeouts, deadline)
+        }
+    }
+
+    /// Replaces the read timeout until reset with `None`, to wait for a response for a limited
+    /// time without treating it as a timeout of the request.
+    pub(crate) fn wait(&mut self, wait: Option<Duration>) {
+        self.wait = wait;
+    }
+
+    fn timeout(&self, timeout: Option<Duration>, elapsed: Elapsed) -> (Option<Duration>, Elapsed) {
+        match self.connecting {
+            true => (self.timeouts.connect, Elapsed::Connect),
+            false => (timeout, elapsed),
+        }
+    }
+}
+
+impl Read for TimedConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (timeout, elapsed) = match self.wait {
+            Some(wait) => (Some(wait), Elapsed::Read),
+            None => self.timeout(self.timeouts.read, Elapsed::Read),
+        };
+        let (timeout, elapsed) = limit(timeout, elapsed, self.deadline)?;
+        self.conn.set_read_timeout(timeout)?;
+        self.conn.read(buf).map_err(|err| elapsed.or(err))
+    }
+}
+
+impl Write for TimedConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (timeout, elapsed) = self.timeout(self.timeouts.write, Elapsed::Write);
+        let (timeout, elapsed) = limit(timeout, elapsed, self.deadline)?;
+        self.conn.set_write_timeout(timeout)?;
+        self.conn.write(buf).map_err(|err| elapsed.or(err))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.conn.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_timeouts_to_the_deadline() {
+        let timeout = Some(Duration::from_secs(1));
+
+        assert_eq!(
+            limit(timeout, Elapsed::Read, None).unwrap(),
+            (timeout, Elapsed::Read)
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(
+            limit(timeout, Elapsed::Read, Some(deadline)).unwrap(),
+            (timeout, Elapsed::Read)
+        );
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert!(matches!(
+            limit(timeout, Elapsed::Read, Some(deadline)).unwrap(),
+            (Some(timeout), Elapsed::Deadline) if timeout <= Duration::from_millis(100)
+        ));
+
+        let err = limit(timeout, Elapsed::Read, Some(Instant::now())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(Elapsed::find(&err), Some(Elapsed::Deadline));
+    }
+}
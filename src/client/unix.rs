@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+/// Sends a request to a Unix socket instead of the host on its URI, when added as one of its
+/// extensions.
+///
+/// The URI still sets the `Host` header and the request target, so local daemons can be called
+/// with URIs such as `http://localhost/containers/json`. Connections to the same socket are
+/// pooled like any other, and proxies are never used for them.
+///
+/// # Example
+/// ```no_run
+/// use touche::{client::UnixSocket, Client};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new();
+///
+/// let res = client.request(
+///     http::Request::builder()
+///         .uri("http://localhost/containers/json")
+///         .extension(UnixSocket::new("/var/run/docker.sock"))
+///         .body(())?,
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnixSocket(PathBuf);
+
+impl UnixSocket {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self(path.into())
+    }
+
+    /// The path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
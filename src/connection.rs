@@ -55,6 +55,26 @@ impl Connection {
         }
     }
 
+    pub fn read_timeout(&self) -> Result<Option<Duration>, io::Error> {
+        match self.0 {
+            ConnectionInner::Tcp(ref tcp) => tcp.read_timeout(),
+            #[cfg(feature = "unix-sockets")]
+            ConnectionInner::Unix(ref unix) => unix.read_timeout(),
+            #[cfg(feature = "rustls")]
+            ConnectionInner::Rustls(ref tls) => tls.read_timeout(),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        match self.0 {
+            ConnectionInner::Tcp(ref tcp) => tcp.set_write_timeout(timeout),
+            #[cfg(feature = "unix-sockets")]
+            ConnectionInner::Unix(ref unix) => unix.set_write_timeout(timeout),
+            #[cfg(feature = "rustls")]
+            ConnectionInner::Rustls(ref tls) => tls.set_write_timeout(timeout),
+        }
+    }
+
     pub fn set_nodelay(&self, nodelay: bool) -> Result<(), io::Error> {
         match self.0 {
             ConnectionInner::Tcp(ref tcp) => tcp.set_nodelay(nodelay),
@@ -65,6 +85,54 @@ impl Connection {
         }
     }
 
+    /// Returns the protocol negotiated through ALPN, if this is a TLS connection that has already
+    /// completed its handshake.
+    pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        match self.0 {
+            #[cfg(feature = "rustls")]
+            ConnectionInner::Rustls(ref tls) => tls.alpn_protocol(),
+            _ => None,
+        }
+    }
+
+    /// Completes the TLS handshake, if this is a TLS connection.
+    pub(crate) fn handshake(&self) -> io::Result<()> {
+        match self.0 {
+            #[cfg(feature = "rustls")]
+            ConnectionInner::Rustls(ref tls) => tls.handshake(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether an idle connection was closed by the peer, or received data nobody asked for.
+    #[cfg(feature = "client")]
+    pub(crate) fn is_stale(&self) -> bool {
+        match self.0 {
+            ConnectionInner::Tcp(ref tcp) => is_stale(
+                |nonblocking| tcp.set_nonblocking(nonblocking),
+                |buf| tcp.peek(buf),
+            ),
+            #[cfg(feature = "unix-sockets")]
+            ConnectionInner::Unix(ref unix) => is_stale(
+                |nonblocking| unix.set_nonblocking(nonblocking),
+                |buf| (&mut &*unix).read(buf),
+            ),
+            #[cfg(feature = "rustls")]
+            ConnectionInner::Rustls(ref tls) => tls.is_stale(),
+        }
+    }
+
+    /// Returns the verified certificates presented by the peer of a TLS connection, if any.
+    #[cfg(feature = "rustls")]
+    pub fn peer_certificates(&self) -> Option<crate::tls::PeerCertificates> {
+        match self.0 {
+            ConnectionInner::Rustls(ref tls) => {
+                crate::tls::PeerCertificates::from_chain(tls.peer_certificates()?)
+            }
+            _ => None,
+        }
+    }
+
     /// Attempts to downcast the [`Connection`] into the underlying stream.
     /// On error returns the [`Connection`] back.
     ///
@@ -97,20 +165,30 @@ impl Connection {
             }
 
             #[cfg(feature = "rustls")]
-            ConnectionInner::Rustls(tls) => match tls.into_inner() {
-                Ok(tls) if Any::type_id(&tls) == TypeId::of::<T>() => {
-                    let tls = Box::new(tls) as Box<dyn Any>;
-                    Ok(tls.downcast().map(|tls| *tls).unwrap())
-                }
-                Ok(tls) => Err(Self(ConnectionInner::Rustls(tls.into()))),
-                Err(tls) => Err(Self(ConnectionInner::Rustls(tls))),
-            },
+            ConnectionInner::Rustls(tls) => tls
+                .downcast()
+                .map_err(|tls| Self(ConnectionInner::Rustls(tls))),
 
             conn => Err(Self(conn)),
         }
     }
 }
 
+#[cfg(feature = "client")]
+pub(crate) fn is_stale(
+    set_nonblocking: impl Fn(bool) -> io::Result<()>,
+    read: impl FnOnce(&mut [u8]) -> io::Result<usize>,
+) -> bool {
+    if set_nonblocking(true).is_err() {
+        return true;
+    }
+
+    let mut buf = [0; 1];
+    let stale = !matches!(read(&mut buf), Err(err) if err.kind() == io::ErrorKind::WouldBlock);
+
+    set_nonblocking(false).is_err() || stale
+}
+
 impl Read for Connection {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
@@ -188,3 +266,10 @@ impl From<rustls::StreamOwned<rustls::ServerConnection, TcpStream>> for Connecti
         Connection(ConnectionInner::Rustls(tls.into()))
     }
 }
+
+#[cfg(feature = "rustls")]
+impl From<rustls::StreamOwned<rustls::ClientConnection, TcpStream>> for Connection {
+    fn from(tls: rustls::StreamOwned<rustls::ClientConnection, TcpStream>) -> Self {
+        Connection(ConnectionInner::Rustls(tls.into()))
+    }
+}
@@ -10,9 +10,12 @@ mod request;
 mod response;
 #[cfg(feature = "server")]
 pub mod server;
+pub mod sse;
 #[cfg(feature = "rustls")]
-mod tls;
+pub mod tls;
 pub mod upgrade;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
 pub use body::Body;
 pub use body::HttpBody;
@@ -2,10 +2,10 @@
 use std::io::Write;
 use std::io::{self, BufRead, Read};
 
-use http::Request;
+use http::{HeaderMap, HeaderName, HeaderValue, Request};
 use thiserror::Error;
 
-use crate::body::Body;
+use crate::body::{Body, Chunk};
 
 #[cfg(feature = "client")]
 use crate::HttpBody;
@@ -90,10 +90,12 @@ pub(crate) fn parse_request(
             // https://datatracker.ietf.org/doc/html/rfc2616#section-3.6
             return Err(ParseError::InvalidTransferEncoding);
         }
-        Body::from_iter(ChunkedReader(Box::new(stream)))
+        Body::from_chunks(ChunkedReader(Box::new(stream)))
     } else if let Some(len) = headers.typed_try_get::<headers::ContentLength>()? {
-        // Let's automatically buffer small bodies
-        if len.0 < 1024 {
+        // Let's automatically buffer small bodies, unless the client waits for `100 Continue`
+        // before sending them
+        let expects_continue = headers.contains_key(http::header::EXPECT);
+        if len.0 < 1024 && !expects_continue {
             let mut buf = vec![0_u8; len.0 as usize];
             stream.read_exact(&mut buf)?;
             Body::from(buf)
@@ -104,16 +106,20 @@ pub(crate) fn parse_request(
         Body::empty()
     };
 
+    // Unread bodies are drained, so the next request on the connection can be read
+    let body = body.on_end(u64::MAX, |_| {});
+
     request.body(body).map_err(|_| ParseError::Unknown)
 }
 
+/// Writes the request line and headers, returning the body to be written after them.
 #[cfg(feature = "client")]
-pub(crate) fn write_request<B: HttpBody>(
+pub(crate) fn write_request_head<B: HttpBody>(
     req: http::Request<B>,
     stream: &mut impl Write,
-) -> io::Result<()> {
-    use crate::{body::Chunk, response::Encoding};
-    use headers::{HeaderMap, HeaderMapExt};
+) -> io::Result<(crate::response::Encoding, B)> {
+    use crate::response::Encoding;
+    use headers::HeaderMapExt;
     use http::{request::Parts, Method, Version};
 
     let (
@@ -140,10 +146,7 @@ pub(crate) fn write_request<B: HttpBody>(
         match (content_length, body.len()) {
             (Some(len), Some(body_len)) => {
                 if len.0 != body_len {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "content-length doesn't match body length",
-                    ));
+                    return Err(io::Error::other("content-length doesn't match body length"));
                 }
                 Encoding::FixedLength(len.0)
             }
@@ -162,10 +165,7 @@ pub(crate) fn write_request<B: HttpBody>(
         headers.typed_insert::<headers::TransferEncoding>(headers::TransferEncoding::chunked());
         Encoding::Chunked
     } else {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "could not determine the size of the body",
-        ));
+        return Err(io::Error::other("could not determine the size of the body"));
     };
 
     let version = if version == Version::HTTP_11 {
@@ -173,10 +173,7 @@ pub(crate) fn write_request<B: HttpBody>(
     } else if version == Version::HTTP_10 {
         "HTTP/1.0"
     } else {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "unsupported http version",
-        ));
+        return Err(io::Error::other("unsupported http version"));
     };
 
     stream.write_all(format!("{method} {uri} {version}\r\n").as_bytes())?;
@@ -187,6 +184,17 @@ pub(crate) fn write_request<B: HttpBody>(
 
     stream.write_all(b"\r\n")?;
 
+    Ok((encoding, body))
+}
+
+#[cfg(feature = "client")]
+pub(crate) fn write_request_body<B: HttpBody>(
+    encoding: crate::response::Encoding,
+    body: B,
+    stream: &mut impl Write,
+) -> io::Result<()> {
+    use crate::{body::Chunk, response::Encoding};
+
     match encoding {
         // Just buffer small bodies
         Encoding::FixedLength(len) if len < 1024 => {
@@ -227,29 +235,77 @@ pub(crate) fn write_request<B: HttpBody>(
 
 pub(crate) struct ChunkedReader(pub(crate) Box<dyn BufRead + Send>);
 
+impl ChunkedReader {
+    fn read_trailers(&mut self) -> io::Result<HeaderMap> {
+        let mut trailers = HeaderMap::new();
+
+        loop {
+            let mut line = Vec::new();
+            if self.0.read_until(b'\n', &mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let line = line
+                .strip_suffix(b"\n")
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .unwrap_or(&line);
+
+            if line.is_empty() {
+                return Ok(trailers);
+            }
+
+            let mut headers = [httparse::EMPTY_HEADER; 1];
+            let line = [line, b"\r\n\r\n"].concat();
+            if let Ok(httparse::Status::Complete((_, [header]))) =
+                httparse::parse_headers(&line, &mut headers)
+            {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(header.name.as_bytes()),
+                    HeaderValue::from_bytes(header.value),
+                ) {
+                    trailers.append(name, value);
+                }
+            }
+        }
+    }
+}
+
 impl Iterator for ChunkedReader {
-    type Item = Vec<u8>;
+    type Item = io::Result<Chunk>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut buf = Vec::new();
 
         loop {
-            if self.0.read_until(b'\n', &mut buf).ok()? == 0 {
-                return None;
+            match self.0.read_until(b'\n', &mut buf) {
+                Ok(0) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
             }
 
             match httparse::parse_chunk_size(&buf) {
                 Ok(httparse::Status::Complete((_pos, 0))) => {
-                    return None;
+                    return match self.read_trailers() {
+                        Ok(trailers) if trailers.is_empty() => None,
+                        Ok(trailers) => Some(Ok(Chunk::Trailers(trailers))),
+                        Err(err) => Some(Err(err)),
+                    };
                 }
                 Ok(httparse::Status::Complete((_pos, size))) => {
                     let mut chunk = vec![0_u8; size as usize];
-                    self.0.read_exact(&mut chunk).ok()?;
-                    self.0.read_until(b'\n', &mut buf).ok()?;
-                    return Some(chunk);
+                    let read = self
+                        .0
+                        .read_exact(&mut chunk)
+                        .and_then(|_| self.0.read_until(b'\n', &mut buf));
+                    return Some(read.map(|_| Chunk::Data(chunk)));
                 }
                 Ok(httparse::Status::Partial) => continue,
-                Err(_) => return None,
+                Err(_) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid chunk size",
+                    )))
+                }
             }
         }
     }
@@ -310,6 +366,23 @@ mod test {
         assert_eq!(req.into_body().into_bytes().unwrap(), b"lolwut");
     }
 
+    #[test]
+    fn parse_request_with_chunked_body_and_trailers() {
+        let req = "POST /lol HTTP/1.1\r\nHost: lol.com\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nlol\r\n0\r\nchecksum: 123\r\n\r\nGET / HTTP/1.1\r\n";
+        let req = std::io::Cursor::new(req);
+
+        let req = parse_request(req).unwrap();
+
+        let chunks = req
+            .into_body()
+            .into_chunks()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert!(matches!(&chunks[0], Chunk::Data(data) if data == b"lol"));
+        assert!(matches!(&chunks[1], Chunk::Trailers(trailers) if trailers["checksum"] == "123"));
+        assert_eq!(chunks.len(), 2);
+    }
+
     #[test]
     fn parse_request_with_streaming_body() {
         let req = b"POST /lol HTTP/1.1\r\nHost: lol.com\r\nContent-Length: 2048\r\n\r\n";
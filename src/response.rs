@@ -3,9 +3,9 @@ use std::io::BufRead;
 use std::io::{self, Write};
 
 use headers::{HeaderMap, HeaderMapExt};
-#[cfg(any(feature = "client", test))]
-use http::StatusCode;
 use http::{response::Parts, Version};
+#[cfg(any(feature = "client", test))]
+use http::{Method, StatusCode};
 
 use crate::{body::Chunk, upgrade::UpgradeExtension, HttpBody};
 #[cfg(any(feature = "client", test))]
@@ -27,10 +27,80 @@ pub(crate) enum Outcome {
     Upgrade(UpgradeExtension),
 }
 
+/// The interim (1xx) responses received before the final one, available as an extension of the
+/// responses that had any.
+///
+/// `101 Switching Protocols` is a final response, so it is never one of them.
+#[cfg(any(feature = "client", test))]
+#[derive(Debug, Clone, Default)]
+pub struct InterimResponses(pub(crate) Vec<http::Response<()>>);
+
+#[cfg(any(feature = "client", test))]
+impl InterimResponses {
+    /// All the interim responses, in the order they were received.
+    pub fn responses(&self) -> &[http::Response<()>] {
+        &self.0
+    }
+}
+
+/// Parses the response to a request with the given method, following the message framing of
+/// [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-6.3). Interim responses are skipped,
+/// and kept on the [`InterimResponses`] extension.
 #[cfg(any(feature = "client", test))]
 pub(crate) fn parse_response(
     mut stream: impl BufRead + Send + 'static,
+    method: &Method,
+) -> Result<http::Response<Body>, ParseError> {
+    let res = parse_head(&mut stream)?;
+    parse_response_from(res, stream, method)
+}
+
+/// Parses the rest of a response whose first head was already read from `stream`.
+#[cfg(any(feature = "client", test))]
+pub(crate) fn parse_response_from(
+    mut res: http::Response<()>,
+    mut stream: impl BufRead + Send + 'static,
+    method: &Method,
 ) -> Result<http::Response<Body>, ParseError> {
+    let mut interim = Vec::new();
+
+    while is_interim(&res) {
+        interim.push(res);
+        res = parse_head(&mut stream)?;
+    }
+
+    let body = if !has_body(method, &res) {
+        Body::empty()
+    } else if let Some(encoding) = res.headers().typed_try_get::<headers::TransferEncoding>()? {
+        if !encoding.is_chunked() {
+            // https://datatracker.ietf.org/doc/html/rfc2616#section-3.6
+            return Err(ParseError::InvalidTransferEncoding);
+        }
+        Body::from_chunks(ChunkedReader(Box::new(stream)))
+    } else if let Some(len) = res.headers().typed_try_get::<headers::ContentLength>()? {
+        // Let's automatically buffer small bodies
+        if len.0 < 1024 {
+            let mut buf = vec![0_u8; len.0 as usize];
+            stream.read_exact(&mut buf)?;
+            Body::from(buf)
+        } else {
+            Body::from_reader(stream, len.0 as usize)
+        }
+    } else {
+        Body::from_reader(stream, None)
+    };
+
+    let mut res = res.map(|_| body);
+
+    if !interim.is_empty() {
+        res.extensions_mut().insert(InterimResponses(interim));
+    }
+
+    Ok(res)
+}
+
+#[cfg(any(feature = "client", test))]
+pub(crate) fn parse_head(stream: &mut impl BufRead) -> Result<http::Response<()>, ParseError> {
     let mut buf = Vec::with_capacity(800);
 
     loop {
@@ -46,7 +116,7 @@ pub(crate) fn parse_response(
     }
 
     if buf.is_empty() {
-        return Err(ParseError::IncompleteRequest);
+        return Err(ParseError::ConnectionClosed);
     }
 
     let mut headers = [httparse::EMPTY_HEADER; 64];
@@ -66,40 +136,39 @@ pub(crate) fn parse_response(
 
     let res = http::Response::builder().version(version).status(status);
 
-    let res = headers
+    headers
         .into_iter()
         .take_while(|header| *header != httparse::EMPTY_HEADER)
         .map(|header| (header.name, header.value))
-        .fold(res, |res, (name, value)| res.header(name, value));
+        .fold(res, |res, (name, value)| res.header(name, value))
+        .body(())
+        .map_err(|_| ParseError::Unknown)
+}
 
-    let headers = res.headers_ref().ok_or(ParseError::Unknown)?;
+/// Whether the response is followed by another one, which `101 Switching Protocols` never is.
+#[cfg(any(feature = "client", test))]
+pub(crate) fn is_interim<B>(res: &http::Response<B>) -> bool {
+    res.status().is_informational() && res.status() != StatusCode::SWITCHING_PROTOCOLS
+}
 
-    let body = if let Some(encoding) = headers.typed_try_get::<headers::TransferEncoding>()? {
-        if !encoding.is_chunked() {
-            // https://datatracker.ietf.org/doc/html/rfc2616#section-3.6
-            return Err(ParseError::InvalidTransferEncoding);
-        }
-        Body::from_iter(ChunkedReader(Box::new(stream)))
-    } else if let Some(len) = headers.typed_try_get::<headers::ContentLength>()? {
-        // Let's automatically buffer small bodies
-        if len.0 < 1024 {
-            let mut buf = vec![0_u8; len.0 as usize];
-            stream.read_exact(&mut buf)?;
-            Body::from(buf)
-        } else {
-            Body::from_reader(stream, len.0 as usize)
-        }
-    } else if headers
-        .typed_get::<headers::Connection>()
-        .filter(|conn| conn.contains("close"))
-        .is_some()
-    {
-        Body::from_reader(stream, None)
-    } else {
-        Body::empty()
-    };
+/// Whether a response to the given method carries a body at all.
+#[cfg(any(feature = "client", test))]
+pub(crate) fn has_body<B>(method: &Method, res: &http::Response<B>) -> bool {
+    let status = res.status();
+
+    !(method == Method::HEAD
+        || status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || (method == Method::CONNECT && status.is_success()))
+}
 
-    res.body(body).map_err(|_| ParseError::Unknown)
+/// Whether the body of the response lasts until the connection is closed, since it has no length.
+#[cfg(any(feature = "client", test))]
+pub(crate) fn is_close_delimited<B>(method: &Method, res: &http::Response<B>) -> bool {
+    has_body(method, res)
+        && !res.headers().contains_key(http::header::TRANSFER_ENCODING)
+        && !res.headers().contains_key(http::header::CONTENT_LENGTH)
 }
 
 #[cfg(feature = "server")]
@@ -137,10 +206,7 @@ pub(crate) fn write_response<B: HttpBody>(
         match (content_length, body.len()) {
             (Some(len), Some(body_len)) => {
                 if len.0 != body_len {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "content-length doesn't match body length",
-                    ));
+                    return Err(io::Error::other("content-length doesn't match body length"));
                 }
                 Encoding::FixedLength(len.0)
             }
@@ -525,7 +591,7 @@ mod tests {
         let res = "HTTP/1.1 200 OK\r\ndate: Mon, 25 Jul 2022 21:34:35 GMT\r\n\r\n";
         let res = Cursor::new(res);
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
 
         assert_eq!(Version::HTTP_11, res.version());
         assert_eq!(StatusCode::OK, res.status());
@@ -542,7 +608,7 @@ mod tests {
         let res = "HTTP/1.1 200 OK\r\ncontent-length: 6\r\n\r\nlolwut ignored";
         let res = Cursor::new(res);
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
 
         assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
     }
@@ -552,7 +618,7 @@ mod tests {
         let res = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n3\r\nlol\r\n3\r\nwut\r\n0\r\n\r\n";
         let res = Cursor::new(res);
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
 
         assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
     }
@@ -562,7 +628,7 @@ mod tests {
         let res = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n3;extension\r\nlol\r\n3\r\nwut\r\n0\r\n\r\n";
         let res = Cursor::new(res);
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
 
         assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
     }
@@ -573,7 +639,7 @@ mod tests {
         let body = [65_u8; 2048];
         let res = Cursor::new([res.as_ref(), body.as_ref()].concat());
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
 
         assert_eq!(res.into_body().into_bytes().unwrap(), body);
     }
@@ -583,8 +649,74 @@ mod tests {
         let res = "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\nlolwut";
         let res = Cursor::new(res);
 
-        let res = parse_response(res).unwrap();
+        let res = parse_response(res, &Method::GET).unwrap();
+
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
+    }
+
+    #[test]
+    fn parse_response_without_body_for_head_requests_and_no_content() {
+        let res = "HTTP/1.1 200 OK\r\ncontent-length: 6\r\n\r\n";
+        let res = parse_response(Cursor::new(res), &Method::HEAD).unwrap();
+        assert_eq!(res.headers()["content-length"], "6");
+        assert!(!is_close_delimited(&Method::HEAD, &res));
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"");
+
+        let res = "HTTP/1.1 204 No Content\r\n\r\n";
+        let res = parse_response(Cursor::new(res), &Method::GET).unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(!is_close_delimited(&Method::GET, &res));
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"");
+    }
+
+    #[test]
+    fn parse_response_without_body_for_not_modified_responses() {
+        let res = "HTTP/1.1 304 Not Modified\r\ncontent-length: 6\r\n\r\n";
+        let res = Cursor::new(res);
+
+        let res = parse_response(res, &Method::GET).unwrap();
+        assert!(!is_close_delimited(&Method::GET, &res));
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"");
+    }
+
+    #[test]
+    fn parse_response_skipping_interim_responses() {
+        let res = "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 6\r\n\r\nlolwut";
+        let res = Cursor::new(res);
+
+        let res = parse_response(res, &Method::GET).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let interim = res.extensions().get::<InterimResponses>().unwrap();
+        assert_eq!(interim.responses().len(), 2);
+        assert_eq!(interim.responses()[0].status(), StatusCode::CONTINUE);
+        assert_eq!(interim.responses()[1].status(), StatusCode::EARLY_HINTS);
+        assert_eq!(
+            interim.responses()[1].headers()["link"],
+            "</style.css>; rel=preload"
+        );
+
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
+    }
+
+    #[test]
+    fn parse_response_with_switching_protocols_as_final_response() {
+        let res = "HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\n\r\nframes";
+        let res = Cursor::new(res);
+
+        let res = parse_response(res, &Method::GET).unwrap();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert!(res.extensions().get::<InterimResponses>().is_none());
+        assert_eq!(res.into_body().into_bytes().unwrap(), b"");
+    }
+
+    #[test]
+    fn parse_response_with_close_delimited_body_without_length() {
+        let res = "HTTP/1.0 200 OK\r\n\r\nlolwut";
+        let res = Cursor::new(res);
 
+        let res = parse_response(res, &Method::GET).unwrap();
+        assert!(is_close_delimited(&Method::GET, &res));
         assert_eq!(res.into_body().into_bytes().unwrap(), b"lolwut");
     }
 }
@@ -21,9 +21,11 @@
 //! }
 //! ```
 use std::{
+    collections::HashMap,
     error::Error,
     io::{self, BufReader, BufWriter, Write},
     net::{TcpListener, ToSocketAddrs},
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
@@ -37,6 +39,7 @@ use crate::{
     read_queue::ReadQueue,
     request::{self, ParseError},
     response::{self, Outcome},
+    upgrade::UpgradeHandler,
     Body, Connection,
 };
 
@@ -122,8 +125,13 @@ pub struct Server<'a> {
     #[cfg(feature = "threadpool")]
     thread_pool: ThreadPool,
     incoming: Box<dyn Iterator<Item = Connection> + 'a>,
+    protocols: Protocols,
 }
 
+/// Handlers for connections that negotiated a non HTTP protocol through ALPN.
+#[derive(Clone, Default)]
+struct Protocols(Arc<HashMap<Vec<u8>, Arc<dyn UpgradeHandler>>>);
+
 impl From<TcpListener> for Server<'static> {
     fn from(listener: TcpListener) -> Self {
         Self::builder().from_connections(TcpAcceptor { listener })
@@ -167,8 +175,9 @@ impl Server<'_> {
     {
         for conn in self.incoming {
             let mut app = service.clone();
+            let protocols = self.protocols.clone();
             self.thread_pool.execute(move || {
-                serve(conn, &mut app).ok();
+                serve(conn, &mut app, &protocols).ok();
             });
         }
 
@@ -197,7 +206,7 @@ impl Server<'_> {
         S: Service,
     {
         for conn in self.incoming {
-            serve(conn, &mut service).ok();
+            serve(conn, &mut service, &self.protocols).ok();
         }
         Ok(())
     }
@@ -255,8 +264,9 @@ impl Server<'_> {
     {
         for conn in self.incoming {
             if let Ok(mut handler) = make_service.call(&conn) {
+                let protocols = self.protocols.clone();
                 self.thread_pool.execute(move || {
-                    serve(conn, &mut handler).ok();
+                    serve(conn, &mut handler, &protocols).ok();
                 });
             }
         }
@@ -270,6 +280,15 @@ pub struct ServerBuilder {
     max_threads: usize,
     read_timeout: Option<Duration>,
     nodelay: bool,
+    #[cfg(feature = "rustls")]
+    tls: Option<(
+        Vec<rustls::pki_types::CertificateDer<'static>>,
+        rustls::pki_types::PrivateKeyDer<'static>,
+    )>,
+    #[cfg(feature = "rustls")]
+    client_auth: Option<crate::tls::ClientAuth>,
+    #[cfg(feature = "rustls")]
+    alpn_protocols: Vec<(Vec<u8>, Arc<dyn UpgradeHandler>)>,
 }
 
 impl Default for ServerBuilder {
@@ -279,6 +298,12 @@ impl Default for ServerBuilder {
             max_threads: 512,
             read_timeout: None,
             nodelay: false,
+            #[cfg(feature = "rustls")]
+            tls: None,
+            #[cfg(feature = "rustls")]
+            client_auth: None,
+            #[cfg(feature = "rustls")]
+            alpn_protocols: Vec::new(),
         }
     }
 }
@@ -384,6 +409,122 @@ impl ServerBuilder {
         Self { nodelay, ..self }
     }
 
+    /// Serves TLS connections with the given certificate chain and private key.
+    ///
+    /// Note that a rustls [`CryptoProvider`](rustls::crypto::CryptoProvider) must be installed as
+    /// the process default before binding the server.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+    /// # use touche::{Response, Server, StatusCode};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let certs = CertificateDer::pem_file_iter("cert.pem")?.collect::<Result<_, _>>()?;
+    /// let key = PrivateKeyDer::from_pem_file("key.pem")?;
+    ///
+    /// Server::builder()
+    ///     .tls(certs, key)
+    ///     .bind("0.0.0.0:4444")
+    ///     .serve(|_req| {
+    ///         Response::builder()
+    ///             .status(StatusCode::OK)
+    ///             .body("Hello from TLS")
+    ///     })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rustls")]
+    pub fn tls(
+        self,
+        certs: Vec<rustls::pki_types::CertificateDer<'static>>,
+        key: rustls::pki_types::PrivateKeyDer<'static>,
+    ) -> Self {
+        Self {
+            tls: Some((certs, key)),
+            ..self
+        }
+    }
+
+    /// Verifies client certificates against a trusted CA bundle (mutual TLS). Only takes effect
+    /// on servers configured with [`ServerBuilder::tls`].
+    ///
+    /// The verified client certificates are available on every request as a
+    /// [`PeerCertificates`](crate::tls::PeerCertificates) extension.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustls::{pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer}, RootCertStore};
+    /// # use touche::{tls::{ClientAuth, PeerCertificates}, Request, Response, Server, StatusCode};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let certs = CertificateDer::pem_file_iter("cert.pem")?.collect::<Result<_, _>>()?;
+    /// # let key = PrivateKeyDer::from_pem_file("key.pem")?;
+    /// let mut roots = RootCertStore::empty();
+    /// for ca in CertificateDer::pem_file_iter("ca.pem")? {
+    ///     roots.add(ca?)?;
+    /// }
+    ///
+    /// Server::builder()
+    ///     .tls(certs, key)
+    ///     .client_auth(ClientAuth::Required(roots))
+    ///     .bind("0.0.0.0:4444")
+    ///     .serve(|req: Request<_>| {
+    ///         let certs = req.extensions().get::<PeerCertificates>();
+    ///         let name = certs.and_then(|certs| certs.common_name()).unwrap_or("anonymous");
+    ///
+    ///         Response::builder()
+    ///             .status(StatusCode::OK)
+    ///             .body(format!("Hello {name}"))
+    ///     })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rustls")]
+    pub fn client_auth(self, client_auth: crate::tls::ClientAuth) -> Self {
+        Self {
+            client_auth: Some(client_auth),
+            ..self
+        }
+    }
+
+    /// Advertises an application protocol through ALPN on TLS servers. Connections negotiating it
+    /// skip HTTP entirely, and are handed straight to the `handler`.
+    ///
+    /// `http/1.1` is always advertised after the registered protocols. HTTP/2 is not supported,
+    /// so clients that only speak `h2` are rejected during the handshake.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// # use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+    /// # use touche::{Connection, Response, Server, StatusCode};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let certs = CertificateDer::pem_file_iter("cert.pem")?.collect::<Result<_, _>>()?;
+    /// # let key = PrivateKeyDer::from_pem_file("key.pem")?;
+    /// Server::builder()
+    ///     .tls(certs, key)
+    ///     .alpn_protocol("echo", |conn: Connection| {
+    ///         io::copy(&mut conn.clone(), &mut conn.clone()).ok();
+    ///     })
+    ///     .bind("0.0.0.0:4444")
+    ///     .serve(|_req| {
+    ///         Response::builder()
+    ///             .status(StatusCode::OK)
+    ///             .body("Hello from HTTP")
+    ///     })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rustls")]
+    pub fn alpn_protocol(
+        mut self,
+        protocol: impl Into<Vec<u8>>,
+        handler: impl UpgradeHandler + 'static,
+    ) -> Self {
+        self.alpn_protocols
+            .push((protocol.into(), Arc::new(handler)));
+        self
+    }
+
     /// Binds the [`Server`] to the given `addr`.
     ///
     /// # Panics
@@ -397,17 +538,48 @@ impl ServerBuilder {
     /// Tries to bind the server to the informed `addr`.
     pub fn try_bind<A: ToSocketAddrs>(self, addr: A) -> io::Result<Server<'static>> {
         let listener = TcpListener::bind(addr)?;
+        self.from_listener(listener)
+    }
+
+    /// Accepts connections from an already bound [`TcpListener`].
+    pub fn from_listener(self, listener: TcpListener) -> io::Result<Server<'static>> {
+        #[cfg(feature = "rustls")]
+        return self.accept_tls(listener);
+
+        #[cfg(not(feature = "rustls"))]
         Ok(self.from_connections(TcpAcceptor { listener }))
     }
 
+    /// Accepts TLS connections from the listener, when a certificate was configured.
+    #[cfg(feature = "rustls")]
+    fn accept_tls(mut self, listener: TcpListener) -> io::Result<Server<'static>> {
+        let Some((certs, key)) = self.tls.take() else {
+            return Ok(self.from_connections(TcpAcceptor { listener }));
+        };
+
+        let protocols = self
+            .alpn_protocols
+            .iter()
+            .map(|(protocol, _)| protocol.clone())
+            .collect();
+        let config = crate::tls::server_config(certs, key, self.client_auth.take(), protocols)?;
+        Ok(self.from_connections(TlsAcceptor { listener, config }))
+    }
+
     /// Accepts connections from some [`Iterator`].
     pub fn from_connections<'a, C: Into<Connection>>(
         self,
         conns: impl IntoIterator<Item = C> + 'a,
     ) -> Server<'a> {
+        #[cfg(feature = "rustls")]
+        let protocols = Protocols(Arc::new(self.alpn_protocols.into_iter().collect()));
+        #[cfg(not(feature = "rustls"))]
+        let protocols = Protocols::default();
+
         Server {
             #[cfg(feature = "threadpool")]
             thread_pool: ThreadPool::new(self.max_threads),
+            protocols,
             incoming: Box::new(conns.into_iter().filter_map(move |conn| {
                 let conn = conn.into();
                 conn.set_read_timeout(self.read_timeout).ok()?;
@@ -430,6 +602,26 @@ impl Iterator for TcpAcceptor {
     }
 }
 
+#[cfg(feature = "rustls")]
+struct TlsAcceptor {
+    listener: TcpListener,
+    config: std::sync::Arc<rustls::ServerConfig>,
+}
+
+#[cfg(feature = "rustls")]
+impl Iterator for TlsAcceptor {
+    type Item = Connection;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (tcp, _addr) = self.listener.accept().ok()?;
+            if let Ok(tls) = rustls::ServerConnection::new(self.config.clone()) {
+                return Some(rustls::StreamOwned::new(tls, tcp).into());
+            }
+        }
+    }
+}
+
 pub trait MakeService {
     type Service: Service;
     type Error: Into<Box<dyn Error + Send + Sync>>;
@@ -451,17 +643,48 @@ where
     }
 }
 
-fn serve<A: Service>(conn: Connection, app: &mut A) -> io::Result<()> {
+fn serve<A: Service>(conn: Connection, app: &mut A, protocols: &Protocols) -> io::Result<()> {
+    if !protocols.0.is_empty() {
+        conn.handshake()?;
+
+        match conn.alpn_protocol() {
+            None => {}
+            Some(protocol) if protocol == b"http/1.1" => {}
+            Some(protocol) => {
+                if let Some(handler) = protocols.0.get(&protocol) {
+                    handler.handle(conn);
+                }
+                return Ok(());
+            }
+        }
+    }
+
     let mut read_queue = ReadQueue::new(BufReader::new(conn.clone()));
 
     let mut reader = read_queue.enqueue();
     let mut writer = BufWriter::new(conn);
 
+    #[cfg(feature = "rustls")]
+    let mut peer_certificates = None;
+
     loop {
         match request::parse_request(reader) {
             Ok(req) => {
                 reader = read_queue.enqueue();
 
+                // The handshake is only guaranteed to be completed after the first request is read
+                #[cfg(feature = "rustls")]
+                let req = {
+                    let mut req = req;
+                    if let Some(certs) = peer_certificates
+                        .get_or_insert_with(|| writer.get_ref().peer_certificates())
+                        .clone()
+                    {
+                        req.extensions_mut().insert(certs);
+                    }
+                    req
+                };
+
                 let asks_for_close = req
                     .headers()
                     .typed_get::<headers::Connection>()
@@ -505,9 +728,7 @@ fn serve<A: Service>(conn: Connection, app: &mut A) -> io::Result<()> {
                     };
                 }
 
-                let mut res = app
-                    .call(req)
-                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                let mut res = app.call(req).map_err(io::Error::other)?;
 
                 *res.version_mut() = version;
 
@@ -540,7 +761,7 @@ fn serve<A: Service>(conn: Connection, app: &mut A) -> io::Result<()> {
                 }
             }
             Err(ParseError::ConnectionClosed) => break,
-            Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err)),
+            Err(err) => return Err(io::Error::other(err)),
         }
     }
 
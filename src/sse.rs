@@ -0,0 +1,643 @@
+//! Server-Sent Events, as specified by the
+//! [HTML standard](https://html.spec.whatwg.org/multipage/server-sent-events.html).
+//!
+//! Streams are answered with [`channel`], which returns the `text/event-stream` response along
+//! with a [`SseSender`] to write [`Events`](Event) on from another thread. They are read with an
+//! [`EventStream`], which clients open with `Client::event_stream`, behind the `client` feature.
+//!
+//! # Example
+//! ```no_run
+//! use std::{thread, time::Duration};
+//!
+//! use touche::{sse::{self, Event}, Body, Request, Server};
+//!
+//! fn main() -> std::io::Result<()> {
+//!     Server::bind("0.0.0.0:4444").serve(|req: Request<Body>| {
+//!         let first = sse::last_event_id(&req)
+//!             .and_then(|id| id.parse::<usize>().ok())
+//!             .map_or(0, |id| id + 1);
+//!
+//!         let (sender, res) = sse::channel();
+//!         let sender = sender.keep_alive(Duration::from_secs(15));
+//!
+//!         thread::spawn(move || {
+//!             for id in first.. {
+//!                 let event = Event::default()
+//!                     .id(id.to_string())
+//!                     .event("tick")
+//!                     .data(format!("Tick number {id}"));
+//!
+//!                 if sender.send(event).is_err() {
+//!                     break;
+//!                 }
+//!                 thread::sleep(Duration::from_secs(1));
+//!             }
+//!         });
+//!
+//!         Ok::<_, std::convert::Infallible>(res)
+//!     })
+//! }
+//! ```
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead, BufReader},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use http::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    HeaderValue, StatusCode,
+};
+
+use crate::{
+    body::{BodyChannel, BodyReader},
+    Body, HttpBody,
+};
+
+#[cfg(feature = "client")]
+mod client;
+
+/// The header on which clients send the id of the last event they received when reconnecting.
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+/// The time clients wait before reconnecting, until the stream sets another one.
+pub const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// The size of the largest event received, unless set with [`EventStream::max_event_size`].
+const DEFAULT_MAX_EVENT_SIZE: usize = 16 * 1024 * 1024;
+
+/// An event of a `text/event-stream`, built field by field.
+///
+/// Its [`Display`] implementation writes it as sent on the stream, blank line included.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Sets the id, which the clients send back as `Last-Event-ID` when they reconnect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id contains a line break or a null character.
+    pub fn id(self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert!(
+            !id.contains(['\r', '\n', '\0']),
+            "event ids must not contain line breaks or null characters"
+        );
+
+        Self {
+            id: Some(id),
+            ..self
+        }
+    }
+
+    /// Sets the type of the event, which defaults to `message` on the clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type contains a line break.
+    pub fn event(self, event: impl Into<String>) -> Self {
+        let event = event.into();
+        assert!(
+            !event.contains(['\r', '\n']),
+            "event types must not contain line breaks"
+        );
+
+        Self {
+            event: Some(event),
+            ..self
+        }
+    }
+
+    /// Sets the data of the event. Data spanning multiple lines is sent on one `data` field per
+    /// line, which clients join back with `\n`.
+    pub fn data(self, data: impl Into<String>) -> Self {
+        Self {
+            data: Some(data.into()),
+            ..self
+        }
+    }
+
+    /// Sets the time clients wait before reconnecting when the stream is closed.
+    pub fn retry(self, retry: Duration) -> Self {
+        Self {
+            retry: Some(retry),
+            ..self
+        }
+    }
+
+    /// Sets a comment, which clients ignore. Comments spanning multiple lines are sent as one
+    /// comment per line.
+    pub fn comment(self, comment: impl Into<String>) -> Self {
+        Self {
+            comment: Some(comment.into()),
+            ..self
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(comment) = &self.comment {
+            for line in lines(comment) {
+                writeln!(f, ": {line}")?;
+            }
+        }
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {event}")?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        if let Some(data) = &self.data {
+            for line in lines(data) {
+                writeln!(f, "data: {line}")?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// Splits on every line ending of the format: `\r\n`, `\n` and a lone `\r`.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+}
+
+/// Creates a `200 OK` response streaming events, and the sender to write them on.
+pub fn channel() -> (SseSender, http::Response<Body>) {
+    let (channel, body) = Body::channel();
+
+    let mut res = http::Response::new(body);
+    *res.status_mut() = StatusCode::OK;
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    res.headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    let sender = SseSender {
+        channel: Arc::new(channel),
+        keep_alive: None,
+    };
+
+    (sender, res)
+}
+
+/// The id of the last event received by a reconnecting client, sent on the `Last-Event-ID`
+/// header.
+pub fn last_event_id<B>(req: &http::Request<B>) -> Option<&str> {
+    req.headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|id| id.to_str().ok())
+}
+
+/// Writes events on a stream created by [`channel`], which ends once the sender is dropped.
+#[derive(Debug)]
+pub struct SseSender {
+    channel: Arc<BodyChannel>,
+    keep_alive: Option<mpsc::Sender<()>>,
+}
+
+impl SseSender {
+    /// Sends an empty comment whenever no event was sent for the interval, so proxies don't
+    /// close idle streams.
+    pub fn keep_alive(self, interval: Duration) -> Self {
+        let (tx, rx) = mpsc::channel::<()>();
+        let channel = self.channel.clone();
+
+        // Stops once the sender is dropped, ending the stream
+        thread::spawn(move || loop {
+            match rx.recv_timeout(interval) {
+                Ok(()) => continue,
+                Err(RecvTimeoutError::Timeout) if channel.send(":\n\n").is_ok() => continue,
+                Err(_) => break,
+            }
+        });
+
+        Self {
+            keep_alive: Some(tx),
+            ..self
+        }
+    }
+
+    /// Sends an event, failing once the client is gone.
+    pub fn send(&self, event: Event) -> io::Result<()> {
+        self.channel.send(event.to_string())?;
+
+        if let Some(keep_alive) = &self.keep_alive {
+            keep_alive.send(()).ok();
+        }
+
+        Ok(())
+    }
+}
+
+/// An event received on an [`EventStream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageEvent {
+    /// The type of the event, `message` unless the stream sets another one.
+    pub event: String,
+    /// The data of the event, its lines joined with `\n`.
+    pub data: String,
+    /// The last id set on the stream when the event was received, which may be from an earlier
+    /// event.
+    pub last_event_id: String,
+}
+
+/// Parses the events of a `text/event-stream` body.
+///
+/// Lines may end with `\r\n`, `\n` or a lone `\r`, and a leading byte order mark is ignored.
+/// Events without data are not yielded, although their `id` and `retry` fields still apply. An
+/// event left incomplete when the body ends is dropped.
+///
+/// # Example
+/// ```no_run
+/// use touche::{sse::EventStream, Body};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let body = Body::from("event: greeting\r\ndata: Hello\r\ndata: world\r\n\r\n");
+///
+/// for event in EventStream::new(body) {
+///     let event = event?;
+///     println!("{}: {}", event.event, event.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct EventStream {
+    reader: BufReader<BodyReader>,
+    started: bool,
+    skip_lf: bool,
+    last_event_id: String,
+    retry: Option<Duration>,
+    max_event_size: usize,
+    #[cfg(feature = "client")]
+    source: Option<client::Source>,
+    #[cfg(feature = "client")]
+    reconnect: bool,
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("last_event_id", &self.last_event_id)
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventStream {
+    /// Reads the events of a body.
+    pub fn new(body: Body) -> Self {
+        Self {
+            reader: BufReader::new(body.into_reader()),
+            started: false,
+            skip_lf: false,
+            last_event_id: String::new(),
+            retry: None,
+            max_event_size: DEFAULT_MAX_EVENT_SIZE,
+            #[cfg(feature = "client")]
+            source: None,
+            #[cfg(feature = "client")]
+            reconnect: false,
+        }
+    }
+
+    /// Sets the maximum size of the events received, counting all of their lines. Larger ones fail
+    /// with an [`io::ErrorKind::InvalidData`] error, which ends the stream. Defaults to 16 MiB.
+    pub fn max_event_size(self, max_event_size: usize) -> Self {
+        Self {
+            max_event_size,
+            ..self
+        }
+    }
+
+    /// The last id set on the stream, which is empty until one is.
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    /// The reconnection time set on the stream, if any.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Streams that were not opened by a client never reconnect.
+    #[cfg(not(feature = "client"))]
+    fn reconnect_source(&mut self) -> Option<io::Result<bool>> {
+        None
+    }
+
+    /// Reads the next event with data, or `None` once the body ends.
+    ///
+    /// The id of an event only becomes the last event id once the event is complete, so streams
+    /// cut in the middle of one resume from the event before.
+    fn read_event(&mut self) -> io::Result<Option<MessageEvent>> {
+        let mut event = String::new();
+        let mut data = String::new();
+        let mut id = None;
+        let mut size = 0;
+
+        while let Some(line) = self.read_line(self.max_event_size - size)? {
+            size += line.len();
+
+            if line.is_empty() {
+                size = 0;
+                if let Some(id) = id.take() {
+                    self.last_event_id = id;
+                }
+
+                if data.is_empty() {
+                    event.clear();
+                    continue;
+                }
+
+                data.pop();
+                if event.is_empty() {
+                    event.push_str("message");
+                }
+
+                return Ok(Some(MessageEvent {
+                    event,
+                    data,
+                    last_event_id: self.last_event_id.clone(),
+                }));
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line.as_str(), ""),
+            };
+
+            match field {
+                "event" => event = value.to_owned(),
+                "data" => {
+                    data.push_str(value);
+                    data.push('\n');
+                }
+                "id" if !value.contains('\0') => id = Some(value.to_owned()),
+                "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                // Comments, which have an empty field name, and unknown fields
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the next line, or `None` once the body ends, dropping a line left unterminated.
+    ///
+    /// Lines longer than the limit end the stream with an error.
+    fn read_line(&mut self, limit: usize) -> io::Result<Option<String>> {
+        let mut line = Vec::new();
+
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+
+            // The line feed of a `\r\n` that was split across reads
+            if std::mem::take(&mut self.skip_lf) && buf[0] == b'\n' {
+                self.reader.consume(1);
+                continue;
+            }
+
+            let ended = match buf.iter().position(|&b| b == b'\r' || b == b'\n') {
+                Some(end) => {
+                    line.extend_from_slice(&buf[..end]);
+                    self.skip_lf = buf[end] == b'\r';
+                    self.reader.consume(end + 1);
+                    true
+                }
+                None => {
+                    let len = buf.len();
+                    line.extend_from_slice(buf);
+                    self.reader.consume(len);
+                    false
+                }
+            };
+
+            if line.len() > limit {
+                // The rest of the event can't be told apart from the next one, and reconnecting
+                // would only receive it again
+                self.reader = BufReader::new(Body::empty().into_reader());
+                #[cfg(feature = "client")]
+                {
+                    self.reconnect = false;
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "event is too large",
+                ));
+            }
+
+            if ended {
+                break;
+            }
+        }
+
+        if !std::mem::replace(&mut self.started, true) && line.starts_with(b"\xef\xbb\xbf") {
+            line.drain(..3);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = io::Result<MessageEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let res = self.read_event();
+            if let Ok(Some(event)) = res {
+                return Some(Ok(event));
+            }
+
+            match self.reconnect_source() {
+                Some(Ok(true)) => continue,
+                Some(Ok(false)) => return None,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return res.transpose(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpBody;
+
+    #[test]
+    fn formats_events() {
+        let event = Event::default()
+            .id("42")
+            .event("message")
+            .retry(Duration::from_secs(3))
+            .data("Hello world");
+
+        assert_eq!(
+            event.to_string(),
+            "event: message\nid: 42\nretry: 3000\ndata: Hello world\n\n"
+        );
+
+        let event = Event::default()
+            .comment("first\nsecond")
+            .data("one\ntwo\r\nthree\rfour\n");
+
+        assert_eq!(
+            event.to_string(),
+            ": first\n: second\ndata: one\ndata: two\ndata: three\ndata: four\ndata: \n\n"
+        );
+
+        assert_eq!(Event::default().to_string(), "\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_ids_with_line_breaks() {
+        let _ = Event::default().id("4\n2");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_types_with_line_breaks() {
+        let _ = Event::default().event("message\r");
+    }
+
+    #[test]
+    fn streams_events_with_keep_alives() {
+        let (sender, res) = channel();
+        assert_eq!(res.headers()["content-type"], "text/event-stream");
+
+        let sender = sender.keep_alive(Duration::from_millis(10));
+        thread::spawn(move || {
+            sender.send(Event::default().data("first")).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            sender.send(Event::default().data("second")).unwrap();
+        });
+
+        let body = String::from_utf8(res.into_body().into_bytes().unwrap()).unwrap();
+        let first = body.find("data: first\n\n").unwrap();
+        let second = body.find("data: second\n\n").unwrap();
+        assert!(body[first..second].contains("\n\n:\n\n"));
+    }
+
+    #[test]
+    fn reads_the_last_event_id() {
+        let req = http::Request::builder()
+            .header("last-event-id", "42")
+            .body(())
+            .unwrap();
+        assert_eq!(last_event_id(&req), Some("42"));
+
+        let req = http::Request::builder().body(()).unwrap();
+        assert_eq!(last_event_id(&req), None);
+    }
+
+    #[test]
+    fn parses_event_streams() {
+        let body = Body::from(
+            "\u{feff}: a comment\r\n\
+             event: greeting\r\n\
+             data: Hello\r\n\
+             data:world\r\n\
+             id: 1\r\n\
+             \r\n\
+             retry: 1500\n\
+             id\n\
+             \n\
+             data\runknown: field\r\r\
+             event: ignored\n\
+             retry: 1s\n\
+             \n\
+             data: incomplete",
+        );
+
+        let mut stream = EventStream::new(body);
+
+        let event = stream.next().unwrap().unwrap();
+        assert_eq!(event.event, "greeting");
+        assert_eq!(event.data, "Hello\nworld");
+        assert_eq!(event.last_event_id, "1");
+
+        let event = stream.next().unwrap().unwrap();
+        assert_eq!(event.event, "message");
+        assert_eq!(event.data, "");
+        assert_eq!(event.last_event_id, "");
+
+        assert!(stream.next().is_none());
+        assert_eq!(stream.retry(), Some(Duration::from_millis(1500)));
+        assert_eq!(stream.last_event_id(), "");
+
+        let mut stream = EventStream::new(Body::from("id: 1\n\nid: 2\ndata: cut"));
+        assert!(stream.next().is_none());
+        assert_eq!(stream.last_event_id(), "1");
+    }
+
+    #[test]
+    fn parses_lines_split_across_reads() {
+        let chunks: [&[u8]; 4] = [b"\xef\xbb", b"\xbfdata: one\r", b"\ndata: two\r", b"\r"];
+        let body = Body::from_iter(chunks.map(<[u8]>::to_vec));
+
+        let events = EventStream::new(body)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "one\ntwo");
+    }
+
+    #[test]
+    fn limits_event_sizes() {
+        let body = Body::from("data: 0123456789\n\ndata: 01234\ndata: 56789\n\ndata: next\n\n");
+        let mut stream = EventStream::new(body).max_event_size(16);
+
+        assert_eq!(stream.next().unwrap().unwrap().data, "0123456789");
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(stream.next().is_none());
+
+        let body = Body::from_iter(["data: ", "0123456789", "0123456789"].map(Vec::from));
+        let mut stream = EventStream::new(body).max_event_size(16);
+        assert!(stream.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_formatted_events() {
+        let event = Event::default()
+            .id("7")
+            .event("update")
+            .comment("ignored")
+            .data("first\r\nsecond");
+
+        let mut stream = EventStream::new(Body::from(event.to_string()));
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            MessageEvent {
+                event: "update".to_owned(),
+                data: "first\nsecond".to_owned(),
+                last_event_id: "7".to_owned(),
+            }
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::{io, io::BufReader, thread};
+
+use http::{
+    header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE},
+    HeaderMap, HeaderValue, StatusCode, Uri,
+};
+
+use super::{EventStream, DEFAULT_RETRY, LAST_EVENT_ID};
+use crate::{client::RequestError, Body, Client, HttpBody};
+
+/// The request a stream was opened with, sent again to reconnect.
+pub(super) struct Source {
+    client: Client,
+    uri: Uri,
+    headers: HeaderMap,
+}
+
+impl Source {
+    fn open(&self, last_event_id: &str) -> Result<http::Response<Body>, RequestError> {
+        let mut req = http::Request::new(());
+        *req.uri_mut() = self.uri.clone();
+        *req.headers_mut() = self.headers.clone();
+
+        if !last_event_id.is_empty() {
+            let id = HeaderValue::from_str(last_event_id).map_err(http::Error::from)?;
+            req.headers_mut().insert(LAST_EVENT_ID, id);
+        }
+
+        self.client.request(req)
+    }
+}
+
+/// Checks that the response is an event stream, returning its body.
+fn event_stream_body(res: http::Response<Body>) -> Result<Body, RequestError> {
+    if res.status() != StatusCode::OK {
+        return Err(RequestError::Status(res.status()));
+    }
+
+    let is_event_stream = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"));
+
+    match is_event_stream {
+        true => Ok(res.into_body()),
+        false => Err(RequestError::NotEventStream),
+    }
+}
+
+impl Client {
+    /// Opens a stream of Server-Sent Events from the URI.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use touche::Client;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let events = Client::new()
+    ///     .event_stream("http://example.com/events")?
+    ///     .reconnect(true);
+    ///
+    /// for event in events {
+    ///     println!("{}", event?.data);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn event_stream<U>(&self, uri: U) -> Result<EventStream, RequestError>
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        let req = http::Request::builder().uri(uri).body(())?;
+        self.event_stream_request(req)
+    }
+
+    /// Opens a stream of Server-Sent Events with the request, which is sent as a `GET` with its
+    /// headers, asking for `text/event-stream` unless it already has an `Accept` header. A
+    /// `Last-Event-ID` header resumes the stream after that event.
+    ///
+    /// Responses other than a `200 OK` with the `text/event-stream` content type are refused.
+    pub fn event_stream_request(
+        &self,
+        req: http::Request<()>,
+    ) -> Result<EventStream, RequestError> {
+        let (parts, ()) = req.into_parts();
+
+        let mut headers = parts.headers;
+        headers
+            .entry(ACCEPT)
+            .or_insert(HeaderValue::from_static("text/event-stream"));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        let last_event_id = headers
+            .remove(LAST_EVENT_ID)
+            .and_then(|id| id.to_str().ok().map(str::to_owned))
+            .unwrap_or_default();
+
+        let source = Source {
+            client: self.clone(),
+            uri: parts.uri,
+            headers,
+        };
+
+        let body = event_stream_body(source.open(&last_event_id)?)?;
+
+        Ok(EventStream {
+            last_event_id,
+            source: Some(source),
+            ..EventStream::new(body)
+        })
+    }
+}
+
+impl EventStream {
+    /// Reconnects whenever the stream ends or fails, once its reconnection time has passed, which
+    /// is [`DEFAULT_RETRY`] unless the stream sets another one. The last event id is sent back on
+    /// the `Last-Event-ID` header.
+    ///
+    /// Reconnecting stops when the server answers with `204 No Content`, and fails when it answers
+    /// with anything else than an event stream, or when the request can't be sent. Connection
+    /// errors and timeouts are retried. Streams don't reconnect again once reconnecting stopped or
+    /// failed, nor after an event larger than [`EventStream::max_event_size`].
+    ///
+    /// Only streams opened by a [`Client`] can reconnect.
+    pub fn reconnect(self, enabled: bool) -> Self {
+        Self {
+            reconnect: enabled,
+            ..self
+        }
+    }
+
+    /// Reconnects to the source, returning whether the stream goes on, or `None` when it doesn't
+    /// reconnect.
+    pub(super) fn reconnect_source(&mut self) -> Option<io::Result<bool>> {
+        if !self.reconnect {
+            return None;
+        }
+        let source = self.source.as_ref()?;
+        self.reconnect = false;
+
+        loop {
+            thread::sleep(self.retry.unwrap_or(DEFAULT_RETRY));
+
+            match source.open(&self.last_event_id) {
+                Ok(res) if res.status() == StatusCode::NO_CONTENT => return Some(Ok(false)),
+                Ok(res) => {
+                    let body = match event_stream_body(res) {
+                        Ok(body) => body,
+                        Err(err) => return Some(Err(io::Error::other(err))),
+                    };
+                    self.reader = BufReader::new(body.into_reader());
+                    self.started = false;
+                    self.skip_lf = false;
+                    self.reconnect = true;
+                    return Some(Ok(true));
+                }
+                Err(
+                    RequestError::Io(_)
+                    | RequestError::ConnectTimeout
+                    | RequestError::ReadTimeout
+                    | RequestError::WriteTimeout
+                    | RequestError::Timeout,
+                ) => continue,
+                Err(err) => return Some(Err(io::Error::other(err))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        sse::{self, Event},
+        Server,
+    };
+
+    fn serve(
+        handler: impl Fn(usize, http::Request<Body>) -> http::Response<Body> + Send + Sync + 'static,
+    ) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        thread::spawn(move || {
+            Server::builder()
+                .max_threads(16)
+                .from_listener(listener)
+                .unwrap()
+                .serve(move |req: http::Request<Body>| {
+                    let n = requests.fetch_add(1, Ordering::SeqCst);
+                    Ok::<_, io::Error>(handler(n, req))
+                })
+                .ok()
+        });
+
+        port
+    }
+
+    #[test]
+    fn reconnects_from_the_last_event_id() {
+        let port = serve(|n, req| match n {
+            0 => {
+                assert_eq!(req.headers()["accept"], "text/event-stream");
+                assert!(!req.headers().contains_key("last-event-id"));
+
+                let (sender, res) = sse::channel();
+                sender
+                    .send(
+                        Event::default()
+                            .id("1")
+                            .retry(Duration::from_millis(10))
+                            .data("first"),
+                    )
+                    .unwrap();
+                res
+            }
+            1 => {
+                assert_eq!(req.headers()["last-event-id"], "1");
+
+                let (sender, res) = sse::channel();
+                sender.send(Event::default().data("second")).unwrap();
+                res
+            }
+            _ => http::Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap(),
+        });
+
+        let events = Client::new()
+            .event_stream(format!("http://127.0.0.1:{port}"))
+            .unwrap()
+            .reconnect(true)
+            .map(|event| event.map(|event| event.data))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(events, ["first", "second"]);
+    }
+
+    #[test]
+    fn resumes_streams_cut_in_the_middle_of_an_event() {
+        let port = serve(|n, req| match n {
+            0 => http::Response::builder()
+                .header(CONTENT_TYPE, "text/event-stream")
+                .body(Body::from(
+                    "retry: 10\nid: 1\ndata: first\n\nid: 2\ndata: cut",
+                ))
+                .unwrap(),
+            1 => {
+                assert_eq!(req.headers()["last-event-id"], "1");
+                http::Response::builder()
+                    .header(CONTENT_TYPE, "text/event-stream")
+                    .body(Body::from("id: 2\ndata: second\n\n"))
+                    .unwrap()
+            }
+            _ => http::Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap(),
+        });
+
+        let events = Client::new()
+            .event_stream(format!("http://127.0.0.1:{port}"))
+            .unwrap()
+            .reconnect(true)
+            .map(|event| event.map(|event| (event.last_event_id, event.data)))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                ("1".to_owned(), "first".to_owned()),
+                ("2".to_owned(), "second".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_reconnecting_once_it_fails() {
+        let port = serve(|n, _req| match n {
+            0 => http::Response::builder()
+                .header(CONTENT_TYPE, "text/event-stream")
+                .body(Body::from("retry: 10\nid: \x7f\ndata: first\n\n"))
+                .unwrap(),
+            _ => http::Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap(),
+        });
+
+        let mut events = Client::new()
+            .event_stream(format!("http://127.0.0.1:{port}"))
+            .unwrap()
+            .reconnect(true);
+
+        assert_eq!(events.next().unwrap().unwrap().data, "first");
+        assert!(events.next().unwrap().is_err());
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn refuses_responses_other_than_event_streams() {
+        let port = serve(|n, _req| match n {
+            0 => http::Response::new(Body::from("data: hello\n\n")),
+            _ => http::Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap(),
+        });
+
+        let client = Client::new();
+        let uri = format!("http://127.0.0.1:{port}");
+
+        assert!(matches!(
+            client.event_stream(&uri),
+            Err(RequestError::NotEventStream)
+        ));
+        assert!(matches!(
+            client.event_stream(&uri),
+            Err(RequestError::Status(StatusCode::NOT_FOUND))
+        ));
+    }
+}
@@ -1,81 +1,706 @@
+//! TLS support, backed by [rustls](https://crates.io/crates/rustls).
 use std::{
+    any::{Any, TypeId},
     io::{self, Read, Write},
-    net::{SocketAddr, TcpStream},
-    sync::{Arc, Mutex},
+    net::{IpAddr, SocketAddr, TcpStream},
+    sync::{Arc, Mutex, MutexGuard},
     time::Duration,
 };
 
-use rustls::{ServerConnection, StreamOwned};
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
 
+/// Client certificate verification mode of a TLS server.
 #[derive(Debug, Clone)]
-pub struct RustlsConnection(Arc<Mutex<StreamOwned<ServerConnection, TcpStream>>>);
+pub enum ClientAuth {
+    /// Clients must present a certificate signed by one of the trusted roots, otherwise the
+    /// handshake fails.
+    Required(RootCertStore),
+    /// Clients may present a certificate signed by one of the trusted roots. Anonymous clients are
+    /// still accepted.
+    Optional(RootCertStore),
+}
 
-impl RustlsConnection {
-    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        let stream = self.0.lock().unwrap();
-        stream.get_ref().set_read_timeout(timeout)?;
-        Ok(())
+/// The verified certificate chain presented by the peer of a TLS connection.
+///
+/// On servers configured with [`ClientAuth`], this is available as an extension of every
+/// request received through a connection where the client presented a certificate.
+///
+/// # Example
+/// ```no_run
+/// # use touche::{tls::PeerCertificates, Body, Request, Response, StatusCode};
+/// fn app(req: Request<Body>) -> http::Result<Response<String>> {
+///     match req.extensions().get::<PeerCertificates>() {
+///         Some(certs) if certs.dns_names().iter().any(|name| name == "billing.internal") => {
+///             Response::builder()
+///                 .status(StatusCode::OK)
+///                 .body(format!("Hello {}", certs.subject()))
+///         }
+///         _ => Response::builder()
+///             .status(StatusCode::FORBIDDEN)
+///             .body(String::new()),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PeerCertificates {
+    chain: Vec<CertificateDer<'static>>,
+    subject: String,
+    common_name: Option<String>,
+    dns_names: Vec<String>,
+    ip_addresses: Vec<IpAddr>,
+    emails: Vec<String>,
+    uris: Vec<String>,
+}
+
+impl PeerCertificates {
+    /// Parses the end entity certificate of the given chain.
+    /// Returns `None` when the chain is empty or the certificate is not a valid X.509 one.
+    pub fn from_chain(chain: Vec<CertificateDer<'static>>) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(chain.first()?).ok()?;
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(String::from);
+
+        let mut certs = PeerCertificates {
+            subject: cert.subject().to_string(),
+            common_name,
+            dns_names: Vec::new(),
+            ip_addresses: Vec::new(),
+            emails: Vec::new(),
+            uris: Vec::new(),
+            chain: Vec::new(),
+        };
+
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) => certs.dns_names.push(name.to_string()),
+                    GeneralName::RFC822Name(email) => certs.emails.push(email.to_string()),
+                    GeneralName::URI(uri) => certs.uris.push(uri.to_string()),
+                    GeneralName::IPAddress(ip) => {
+                        if let Ok(ip) = <[u8; 4]>::try_from(*ip) {
+                            certs.ip_addresses.push(ip.into());
+                        } else if let Ok(ip) = <[u8; 16]>::try_from(*ip) {
+                            certs.ip_addresses.push(ip.into());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        drop(cert);
+        certs.chain = chain;
+
+        Some(certs)
     }
 
-    pub(crate) fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
-        let stream = self.0.lock().unwrap();
-        stream.get_ref().set_nodelay(nodelay)?;
-        Ok(())
+    /// The full certificate chain, starting with the end entity certificate.
+    pub fn chain(&self) -> &[CertificateDer<'static>] {
+        &self.chain
     }
 
-    pub(crate) fn into_inner(self) -> Result<StreamOwned<ServerConnection, TcpStream>, Self> {
-        match Arc::try_unwrap(self.0) {
-            Ok(conn) => Ok(conn.into_inner().unwrap()),
-            Err(err) => Err(Self(err)),
-        }
+    /// The end entity certificate.
+    pub fn end_entity(&self) -> &CertificateDer<'static> {
+        &self.chain[0]
+    }
+
+    /// The subject distinguished name of the end entity certificate, formatted as in RFC 4514
+    /// (e.g. `CN=billing, O=Acme`).
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The first common name (CN) of the certificate subject.
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    /// DNS names from the subject alternative name extension.
+    pub fn dns_names(&self) -> &[String] {
+        &self.dns_names
+    }
+
+    /// IP addresses from the subject alternative name extension.
+    pub fn ip_addresses(&self) -> &[IpAddr] {
+        &self.ip_addresses
+    }
+
+    /// Email addresses from the subject alternative name extension.
+    pub fn emails(&self) -> &[String] {
+        &self.emails
+    }
+
+    /// URIs (such as SPIFFE ids) from the subject alternative name extension.
+    pub fn uris(&self) -> &[String] {
+        &self.uris
     }
 }
 
-impl From<StreamOwned<ServerConnection, TcpStream>> for RustlsConnection {
-    fn from(tls: StreamOwned<ServerConnection, TcpStream>) -> Self {
-        RustlsConnection(Arc::new(Mutex::new(tls)))
+pub(crate) fn server_config(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_auth: Option<ClientAuth>,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> io::Result<Arc<ServerConfig>> {
+    let builder = ServerConfig::builder();
+
+    let builder = match client_auth {
+        None => builder.with_no_client_auth(),
+        Some(ClientAuth::Required(roots)) => builder.with_client_cert_verifier(
+            WebPkiClientVerifier::builder(roots.into())
+                .build()
+                .map_err(io::Error::other)?,
+        ),
+        Some(ClientAuth::Optional(roots)) => builder.with_client_cert_verifier(
+            WebPkiClientVerifier::builder(roots.into())
+                .allow_unauthenticated()
+                .build()
+                .map_err(io::Error::other)?,
+        ),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(io::Error::other)?;
+
+    // HTTP/2 is not supported, so it is never negotiated, even if explicitly asked to
+    config.alpn_protocols = alpn_protocols
+        .into_iter()
+        .filter(|protocol| protocol != b"h2")
+        .collect();
+    if !config
+        .alpn_protocols
+        .iter()
+        .any(|protocol| protocol == b"http/1.1")
+    {
+        config.alpn_protocols.push(b"http/1.1".to_vec());
     }
+
+    Ok(Arc::new(config))
+}
+
+/// A TLS stream that can be read and written at the same time from different threads.
+///
+/// The rustls state machine is kept behind a lock, but the lock is never held while waiting for
+/// data from the socket, so a blocked reader doesn't stall writers.
+#[derive(Debug, Clone)]
+pub struct RustlsConnection(Arc<RustlsStream>);
+
+#[derive(Debug)]
+struct RustlsStream {
+    tls: Mutex<rustls::Connection>,
+    sock: TcpStream,
+    // Serializes socket reads, so TLS records are always fed in order
+    reading: Mutex<()>,
 }
 
 impl RustlsConnection {
+    pub(crate) fn new(tls: impl Into<rustls::Connection>, sock: TcpStream) -> Self {
+        RustlsConnection(Arc::new(RustlsStream {
+            tls: Mutex::new(tls.into()),
+            sock,
+            reading: Mutex::new(()),
+        }))
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.sock.set_read_timeout(timeout)
+    }
+
+    pub(crate) fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.sock.read_timeout()
+    }
+
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.sock.set_write_timeout(timeout)
+    }
+
+    pub(crate) fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.0.sock.set_nodelay(nodelay)
+    }
+
+    pub(crate) fn peer_certificates(&self) -> Option<Vec<CertificateDer<'static>>> {
+        let tls = self.0.tls.lock().ok()?;
+        Some(tls.peer_certificates()?.to_vec())
+    }
+
+    pub(crate) fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        let tls = self.0.tls.lock().ok()?;
+        Some(tls.alpn_protocol()?.to_vec())
+    }
+
+    pub(crate) fn handshake(&self) -> io::Result<()> {
+        self.0.lock().map(|_| ())
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn is_stale(&self) -> bool {
+        crate::connection::is_stale(
+            |nonblocking| self.0.sock.set_nonblocking(nonblocking),
+            |buf| self.0.sock.peek(buf),
+        )
+    }
+
+    pub(crate) fn downcast<T: Any>(self) -> Result<T, Self> {
+        let server = TypeId::of::<StreamOwned<ServerConnection, TcpStream>>();
+        let client = TypeId::of::<StreamOwned<ClientConnection, TcpStream>>();
+
+        if TypeId::of::<T>() != server && TypeId::of::<T>() != client {
+            return Err(self);
+        }
+
+        let RustlsStream { tls, sock, .. } = Arc::try_unwrap(self.0).map_err(Self)?;
+
+        let stream: Box<dyn Any> = match tls.into_inner().unwrap() {
+            rustls::Connection::Server(tls) if TypeId::of::<T>() == server => {
+                Box::new(StreamOwned::new(tls, sock))
+            }
+            rustls::Connection::Client(tls) if TypeId::of::<T>() == client => {
+                Box::new(StreamOwned::new(tls, sock))
+            }
+            tls => return Err(Self::new(tls, sock)),
+        };
+
+        Ok(stream.downcast().map(|stream| *stream).unwrap())
+    }
+
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        self.0
-            .lock()
-            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "Failed to aquire lock"))?
-            .sock
-            .peer_addr()
+        self.0.sock.peer_addr()
     }
 
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.0.sock.local_addr()
+    }
+}
+
+impl RustlsStream {
+    fn lock(&self) -> io::Result<MutexGuard<'_, rustls::Connection>> {
+        let mut tls = self
+            .tls
             .lock()
-            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "Failed to aquire lock"))?
-            .sock
-            .local_addr()
+            .map_err(|_err| io::Error::other("Failed to aquire lock"))?;
+
+        if tls.is_handshaking() {
+            tls.complete_io(&mut &self.sock)?;
+        }
+
+        Ok(tls)
+    }
+
+    fn flush_tls(&self, tls: &mut rustls::Connection) -> io::Result<()> {
+        while tls.wants_write() {
+            tls.write_tls(&mut &self.sock)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<StreamOwned<ServerConnection, TcpStream>> for RustlsConnection {
+    fn from(tls: StreamOwned<ServerConnection, TcpStream>) -> Self {
+        RustlsConnection::new(tls.conn, tls.sock)
+    }
+}
+
+impl From<StreamOwned<ClientConnection, TcpStream>> for RustlsConnection {
+    fn from(tls: StreamOwned<ClientConnection, TcpStream>) -> Self {
+        RustlsConnection::new(tls.conn, tls.sock)
     }
 }
 
 impl Read for RustlsConnection {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.0
+        let stream = &self.0;
+        let _reading = stream
+            .reading
             .lock()
-            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "Failed to aquire lock"))?
-            .read(buf)
+            .map_err(|_err| io::Error::other("Failed to aquire lock"))?;
+
+        loop {
+            match stream.lock()?.reader().read(buf) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+
+            // No plaintext available, so wait for more records without holding the TLS lock
+            let mut incoming = [0_u8; 16 * 1024];
+            let len = (&stream.sock).read(&mut incoming)?;
+            let mut incoming = &incoming[..len];
+
+            let mut tls = stream.lock()?;
+            loop {
+                tls.read_tls(&mut incoming)?;
+                if let Err(err) = tls.process_new_packets() {
+                    // Try to tell the peer what went wrong before bailing out
+                    stream.flush_tls(&mut tls).ok();
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+                }
+                if incoming.is_empty() {
+                    break;
+                }
+            }
+            stream.flush_tls(&mut tls)?;
+        }
     }
 }
 
 impl Write for RustlsConnection {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0
-            .lock()
-            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "Failed to aquire lock"))?
-            .write(buf)
+        let mut tls = self.0.lock()?;
+        let len = tls.writer().write(buf)?;
+        self.0.flush_tls(&mut tls)?;
+        Ok(len)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0
-            .lock()
-            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "Failed to aquire lock"))?
-            .flush()
+        let mut tls = self.0.lock()?;
+        tls.writer().flush()?;
+        self.0.flush_tls(&mut tls)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{net::TcpListener, thread};
+
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair, SanType};
+    use rustls::{
+        pki_types::{PrivatePkcs8KeyDer, ServerName},
+        ClientConfig, ClientConnection,
+    };
+
+    use crate::{
+        server::{ServerBuilder, Service},
+        upgrade::Upgrade,
+        Body, Connection, Request, Response, Server, StatusCode,
+    };
+
+    use super::*;
+
+    pub(crate) struct Ca {
+        cert: Certificate,
+        key: KeyPair,
+    }
+
+    impl Ca {
+        pub(crate) fn new() -> Self {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "Test CA");
+            let cert = params.self_signed(&key).unwrap();
+            Ca { cert, key }
+        }
+
+        pub(crate) fn roots(&self) -> RootCertStore {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.cert.der().clone()).unwrap();
+            roots
+        }
+
+        pub(crate) fn sign(
+            &self,
+            params: CertificateParams,
+        ) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            let key = PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+            (vec![cert.der().clone()], key)
+        }
+    }
+
+    fn serve<F, S>(ca: &Ca, configure: F, app: S) -> u16
+    where
+        F: FnOnce(ServerBuilder) -> ServerBuilder + Send + 'static,
+        S: Service + Send + Clone + 'static,
+    {
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .ok();
+
+        let (certs, key) = ca.sign(CertificateParams::new(vec!["localhost".into()]).unwrap());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            configure(Server::builder().tls(certs, key))
+                .from_listener(listener)
+                .unwrap()
+                .serve(app)
+                .ok()
+        });
+
+        port
+    }
+
+    fn whoami(req: Request<Body>) -> http::Result<Response<String>> {
+        let body = match req.extensions().get::<PeerCertificates>() {
+            Some(certs) => format!(
+                "{}|{}|{}|{}",
+                certs.subject(),
+                certs.common_name().unwrap_or_default(),
+                certs.dns_names().join(","),
+                certs.uris().join(","),
+            ),
+            None => "anonymous".to_string(),
+        };
+        Response::builder().header("connection", "close").body(body)
+    }
+
+    fn connect(
+        ca: &Ca,
+        port: u16,
+        identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+    ) -> StreamOwned<ClientConnection, TcpStream> {
+        connect_with_alpn(ca, port, identity, Vec::new())
+    }
+
+    fn connect_with_alpn(
+        ca: &Ca,
+        port: u16,
+        identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+        alpn_protocols: Vec<Vec<u8>>,
+    ) -> StreamOwned<ClientConnection, TcpStream> {
+        let config = ClientConfig::builder().with_root_certificates(ca.roots());
+        let mut config = match identity {
+            Some((certs, key)) => config.with_client_auth_cert(certs, key).unwrap(),
+            None => config.with_no_client_auth(),
+        };
+        config.alpn_protocols = alpn_protocols;
+
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let tls = ClientConnection::new(Arc::new(config), server_name).unwrap();
+        let tcp = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        tcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        StreamOwned::new(tls, tcp)
+    }
+
+    fn request(
+        ca: &Ca,
+        port: u16,
+        identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+    ) -> String {
+        let mut stream = connect(ca, port, identity);
+
+        let mut buf = Vec::new();
+        if stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .is_ok()
+        {
+            stream.read_to_end(&mut buf).ok();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn exposes_verified_client_certificates_to_requests() {
+        let ca = Ca::new();
+        let port = serve(
+            &ca,
+            {
+                let roots = ca.roots();
+                move |server| server.client_auth(ClientAuth::Required(roots))
+            },
+            whoami,
+        );
+
+        let mut params = CertificateParams::new(vec!["billing.internal".into()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "billing");
+        params
+            .subject_alt_names
+            .push(SanType::URI("spiffe://acme/billing".try_into().unwrap()));
+
+        let res = request(&ca, port, Some(ca.sign(params)));
+
+        assert!(res.starts_with("HTTP/1.1 200 OK"));
+        assert!(res.ends_with("CN=billing|billing|billing.internal|spiffe://acme/billing"));
+    }
+
+    #[test]
+    fn rejects_anonymous_clients_when_client_auth_is_required() {
+        let ca = Ca::new();
+        let port = serve(
+            &ca,
+            {
+                let roots = ca.roots();
+                move |server| server.client_auth(ClientAuth::Required(roots))
+            },
+            whoami,
+        );
+
+        assert!(request(&ca, port, None).is_empty());
+    }
+
+    #[test]
+    fn rejects_clients_with_certificates_from_untrusted_authorities() {
+        let ca = Ca::new();
+        let port = serve(
+            &ca,
+            {
+                let roots = ca.roots();
+                move |server| server.client_auth(ClientAuth::Optional(roots))
+            },
+            whoami,
+        );
+
+        let untrusted = Ca::new();
+        let identity = untrusted.sign(CertificateParams::new(vec!["evil".into()]).unwrap());
+
+        assert!(request(&ca, port, Some(identity)).is_empty());
+    }
+
+    #[test]
+    fn accepts_anonymous_clients_when_client_auth_is_optional() {
+        let ca = Ca::new();
+        let port = serve(
+            &ca,
+            {
+                let roots = ca.roots();
+                move |server| server.client_auth(ClientAuth::Optional(roots))
+            },
+            whoami,
+        );
+
+        let res = request(&ca, port, None);
+
+        assert!(res.starts_with("HTTP/1.1 200 OK"));
+        assert!(res.ends_with("anonymous"));
+    }
+
+    #[test]
+    fn reads_and_writes_upgraded_connections_at_the_same_time() {
+        let ca = Ca::new();
+        let port = serve(
+            &ca,
+            |server| server,
+            |_req| {
+                Response::builder()
+                    .status(StatusCode::SWITCHING_PROTOCOLS)
+                    .header("upgrade", "duplex")
+                    .upgrade(|mut conn: Connection| {
+                        let mut reader = conn.clone();
+                        let echo = thread::spawn(move || {
+                            let mut buf = [0_u8; 4];
+                            reader.read_exact(&mut buf).unwrap();
+                            reader.write_all(&buf).unwrap();
+                        });
+
+                        // Gives the reader some time to block waiting for data
+                        thread::sleep(Duration::from_millis(100));
+                        conn.write_all(b"hello").unwrap();
+
+                        echo.join().unwrap();
+                    })
+                    .body(())
+            },
+        );
+
+        let mut stream = connect(&ca, port, None);
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nupgrade: duplex\r\n\r\n")
+            .unwrap();
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0_u8; 1];
+            stream.read_exact(&mut byte).unwrap();
+            head.extend(byte);
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101 Switching Protocols"));
+
+        let mut buf = [0_u8; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+
+        stream.write_all(b"ping").unwrap();
+        let mut buf = [0_u8; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    fn serve_with_alpn(ca: &Ca) -> u16 {
+        serve(
+            ca,
+            |server| {
+                server.alpn_protocol("echo", |mut conn: Connection| {
+                    let mut buf = [0_u8; 4];
+                    conn.read_exact(&mut buf).unwrap();
+                    conn.write_all(&buf).unwrap();
+                })
+            },
+            |_req| {
+                Response::builder()
+                    .header("connection", "close")
+                    .body("http")
+            },
+        )
+    }
+
+    #[test]
+    fn lists_http11_once_in_alpn_protocols() {
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .ok();
+
+        let ca = Ca::new();
+        let (certs, key) = ca.sign(CertificateParams::new(vec!["localhost".into()]).unwrap());
+        let protocols = vec![b"http/1.1".to_vec(), b"h2".to_vec(), b"echo".to_vec()];
+
+        let config = server_config(certs, key, None, protocols).unwrap();
+        assert_eq!(
+            config.alpn_protocols,
+            [b"http/1.1".to_vec(), b"echo".to_vec()]
+        );
+    }
+
+    #[test]
+    fn dispatches_connections_by_negotiated_alpn_protocol() {
+        let ca = Ca::new();
+        let port = serve_with_alpn(&ca);
+
+        let mut stream = connect_with_alpn(&ca, port, None, vec![b"echo".to_vec()]);
+        stream.write_all(b"ping").unwrap();
+        let mut buf = [0_u8; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(stream.conn.alpn_protocol(), Some(&b"echo"[..]));
+        assert_eq!(&buf, b"ping");
+
+        let mut stream =
+            connect_with_alpn(&ca, port, None, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).ok();
+        assert_eq!(stream.conn.alpn_protocol(), Some(&b"http/1.1"[..]));
+        assert!(res.ends_with("http"));
+    }
+
+    #[test]
+    fn rejects_clients_that_only_speak_h2() {
+        let ca = Ca::new();
+        let port = serve_with_alpn(&ca);
+
+        let mut stream = connect_with_alpn(&ca, port, None, vec![b"h2".to_vec()]);
+        let err = stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .and_then(|_| stream.read_to_end(&mut Vec::new()))
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
     }
 }
@@ -0,0 +1,698 @@
+//! WebSocket connections, as specified by [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455).
+//!
+//! Clients open them with [`Client::websocket`](crate::Client::websocket), which performs the
+//! opening handshake and returns a [`WebSocket`] to exchange messages on. Servers accept them with
+//! [`WebSocketUpgrade`], which answers the handshake and hands the [`WebSocket`] to a handler.
+//!
+//! Messages can be compressed with the `permessage-deflate` extension, behind the
+//! `websocket-deflate` feature.
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io::{self, BufReader, Chain, Cursor, Read},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+#[cfg(feature = "client")]
+use crate::client::RequestError;
+use crate::Connection;
+
+use self::frame::{Frame, OpCode};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "websocket-deflate")]
+mod deflate;
+mod frame;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "server")]
+pub use self::server::WebSocketUpgrade;
+
+/// The size of the frames messages are split into, unless set with [`WebSocket::frame_size`].
+const DEFAULT_FRAME_SIZE: usize = 64 * 1024;
+
+/// The size of the largest message received, unless set with [`WebSocket::max_message_size`].
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum WebSocketError {
+    #[cfg(feature = "client")]
+    #[error("handshake request failed")]
+    Request(#[from] RequestError),
+    #[error("server refused the handshake with {0}")]
+    Rejected(http::StatusCode),
+    #[error("invalid handshake: {0}")]
+    Handshake(&'static str),
+    #[error("protocol error: {0}")]
+    Protocol(&'static str),
+    #[error("text message is not valid utf-8")]
+    InvalidUtf8,
+    #[error("message is too big")]
+    MessageTooBig,
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("io error")]
+    Io(#[from] io::Error),
+}
+
+/// Which end of the connection a [`WebSocket`] is. Clients mask the frames they send, and servers
+/// must not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// A message sent or received on a [`WebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseFrame>),
+}
+
+impl Message {
+    pub fn text(text: impl Into<String>) -> Self {
+        Message::Text(text.into())
+    }
+
+    pub fn binary(data: impl Into<Vec<u8>>) -> Self {
+        Message::Binary(data.into())
+    }
+}
+
+/// The status code of a close frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CloseCode(pub u16);
+
+impl CloseCode {
+    pub const NORMAL: CloseCode = CloseCode(1000);
+    pub const GOING_AWAY: CloseCode = CloseCode(1001);
+    pub const PROTOCOL_ERROR: CloseCode = CloseCode(1002);
+    pub const UNSUPPORTED_DATA: CloseCode = CloseCode(1003);
+    pub const INVALID_PAYLOAD: CloseCode = CloseCode(1007);
+    pub const POLICY_VIOLATION: CloseCode = CloseCode(1008);
+    pub const MESSAGE_TOO_BIG: CloseCode = CloseCode(1009);
+    pub const MANDATORY_EXTENSION: CloseCode = CloseCode(1010);
+    pub const INTERNAL_ERROR: CloseCode = CloseCode(1011);
+}
+
+impl CloseCode {
+    /// Parses a received status code, which must be one peers are allowed to send.
+    fn from_bytes(bytes: [u8; 2]) -> Result<Self, WebSocketError> {
+        match u16::from_be_bytes(bytes) {
+            // 1005, 1006 and 1015 only stand for missing codes, and are never sent
+            code @ (1000..=1003 | 1007..=1014 | 3000..=4999) => Ok(CloseCode(code)),
+            _ => Err(WebSocketError::Protocol("invalid close code")),
+        }
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Why a [`WebSocket`] was closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    pub fn new(code: CloseCode, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    fn parse(payload: &[u8]) -> Result<Option<Self>, WebSocketError> {
+        match payload {
+            [] => Ok(None),
+            [_] => Err(WebSocketError::Protocol(
+                "close frame with a truncated code",
+            )),
+            [high, low, reason @ ..] => Ok(Some(Self {
+                code: CloseCode::from_bytes([*high, *low])?,
+                reason: String::from_utf8(reason.to_vec())
+                    .map_err(|_| WebSocketError::InvalidUtf8)?,
+            })),
+        }
+    }
+
+    fn payload(&self) -> Vec<u8> {
+        [&self.code.0.to_be_bytes()[..], self.reason.as_bytes()].concat()
+    }
+}
+
+/// A WebSocket connection, exchanging messages with blocking reads and writes.
+///
+/// Messages are split into frames of up to [`WebSocket::frame_size`] bytes when sent, and
+/// fragmented messages are put back together when received. Pings are answered automatically, but
+/// still returned by [`WebSocket::read`].
+#[derive(Debug)]
+pub struct WebSocket {
+    reader: BufReader<Chain<Cursor<Vec<u8>>, Connection>>,
+    writer: Arc<Mutex<Writer>>,
+    conn: Connection,
+    role: Role,
+    protocol: Option<String>,
+    max_message_size: usize,
+    fragments: Option<Fragments>,
+    close_received: bool,
+    keep_alive: Option<mpsc::Sender<()>>,
+}
+
+/// The opcode of a fragmented message, whether it is compressed, and its payload so far.
+type Fragments = (OpCode, bool, Vec<u8>);
+
+impl WebSocket {
+    /// Speaks WebSocket on a connection that already went through the opening handshake.
+    pub fn from_connection(connection: impl Into<Connection>, role: Role) -> Self {
+        Self::with_read_ahead(connection.into(), role, Vec::new())
+    }
+
+    /// Speaks WebSocket on a connection, reading `read_ahead` before anything else from it.
+    pub(crate) fn with_read_ahead(conn: Connection, role: Role, read_ahead: Vec<u8>) -> Self {
+        Self {
+            reader: BufReader::new(Cursor::new(read_ahead).chain(conn.clone())),
+            writer: Arc::new(Mutex::new(Writer {
+                conn: conn.clone(),
+                role,
+                frame_size: DEFAULT_FRAME_SIZE,
+                deflate: false,
+                closed: false,
+            })),
+            conn,
+            role,
+            protocol: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            fragments: None,
+            close_received: false,
+            keep_alive: None,
+        }
+    }
+
+    /// Sets the maximum size of the frames sent, larger messages being fragmented. Defaults to
+    /// 64 KiB.
+    pub fn frame_size(self, frame_size: usize) -> Self {
+        lock(&self.writer).frame_size = frame_size.max(1);
+        self
+    }
+
+    /// Sets the maximum size of the messages received. Larger ones close the connection with
+    /// [`CloseCode::MESSAGE_TOO_BIG`]. Defaults to 64 MiB.
+    pub fn max_message_size(self, max_message_size: usize) -> Self {
+        Self {
+            max_message_size,
+            ..self
+        }
+    }
+
+    /// Sends a ping at every interval from a background thread, keeping idle connections open
+    /// through proxies and load balancers. The pongs are returned by [`WebSocket::read`].
+    pub fn ping_interval(self, interval: Duration) -> Self {
+        let (tx, rx) = mpsc::channel::<()>();
+        let writer = self.writer.clone();
+
+        // Stops once the socket is dropped, since nothing is ever sent on the channel
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(interval) {
+                let mut writer = lock(&writer);
+                if writer.closed
+                    || writer
+                        .write_frame(Frame::new(OpCode::Ping, Vec::new()))
+                        .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            keep_alive: Some(tx),
+            ..self
+        }
+    }
+
+    /// Compresses the messages with the `permessage-deflate` extension, once negotiated.
+    #[cfg(feature = "websocket-deflate")]
+    pub(crate) fn deflate(self, deflate: bool) -> Self {
+        lock(&self.writer).deflate = deflate;
+        self
+    }
+
+    /// The subprotocol agreed on during the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// The connection underneath.
+    pub fn get_ref(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// A handle to send messages from other threads, while this one reads.
+    pub fn sender(&self) -> WebSocketSender {
+        WebSocketSender {
+            writer: self.writer.clone(),
+        }
+    }
+
+    /// Reads the next message.
+    ///
+    /// Once the peer starts the closing handshake, the close frame is answered and returned as a
+    /// [`Message::Close`]. Reading after that fails with [`WebSocketError::ConnectionClosed`], as it
+    /// does after any error, the connection being closed once reading or writing it failed.
+    pub fn read(&mut self) -> Result<Message, WebSocketError> {
+        loop {
+            if self.close_received {
+                return Err(WebSocketError::ConnectionClosed);
+            }
+
+            let masked = self.role == Role::Server;
+            let frame = Frame::read(&mut self.reader, masked, self.max_message_size)
+                .map_err(|err| self.fail(err))?;
+
+            if frame.rsv1 && (frame.opcode.is_control() || frame.opcode == OpCode::Continuation) {
+                return Err(self.fail(WebSocketError::Protocol("reserved bits are set")));
+            }
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    let mut writer = lock(&self.writer);
+                    if !writer.closed {
+                        let pong =
+                            writer.write_frame(Frame::new(OpCode::Pong, frame.payload.clone()));
+                        drop(writer);
+                        pong.map_err(|err| self.fail(err))?;
+                    }
+                    return Ok(Message::Ping(frame.payload));
+                }
+                OpCode::Pong => return Ok(Message::Pong(frame.payload)),
+                OpCode::Close => return self.close_received(&frame.payload),
+                _ => {}
+            }
+
+            let (opcode, compressed, payload) = match (frame.opcode, self.fragments.take()) {
+                (OpCode::Continuation, Some((opcode, compressed, mut payload))) => {
+                    if payload.len() + frame.payload.len() > self.max_message_size {
+                        return Err(self.fail(WebSocketError::MessageTooBig));
+                    }
+                    payload.extend_from_slice(&frame.payload);
+                    (opcode, compressed, payload)
+                }
+                (OpCode::Continuation, None) => {
+                    return Err(self.fail(WebSocketError::Protocol(
+                        "continuation frame without a message",
+                    )))
+                }
+                (_, Some(_)) => {
+                    return Err(self.fail(WebSocketError::Protocol(
+                        "new message before the end of a fragmented one",
+                    )))
+                }
+                (opcode, None) => {
+                    if frame.rsv1 && !lock(&self.writer).deflate {
+                        return Err(self.fail(WebSocketError::Protocol("reserved bits are set")));
+                    }
+                    (opcode, frame.rsv1, frame.payload)
+                }
+            };
+
+            if !frame.fin {
+                self.fragments = Some((opcode, compressed, payload));
+                continue;
+            }
+
+            #[cfg(feature = "websocket-deflate")]
+            let payload = match compressed {
+                true => deflate::decompress(&payload, self.max_message_size)
+                    .map_err(|err| self.fail(err))?,
+                false => payload,
+            };
+            #[cfg(not(feature = "websocket-deflate"))]
+            let _ = compressed;
+
+            return match opcode {
+                OpCode::Text => match String::from_utf8(payload) {
+                    Ok(text) => Ok(Message::Text(text)),
+                    Err(_) => Err(self.fail(WebSocketError::InvalidUtf8)),
+                },
+                _ => Ok(Message::Binary(payload)),
+            };
+        }
+    }
+
+    /// Sends a message. Sending a [`Message::Close`] performs the closing handshake, as
+    /// [`WebSocket::close`] does.
+    pub fn send(&mut self, msg: Message) -> Result<(), WebSocketError> {
+        match msg {
+            Message::Close(frame) => self.close(frame),
+            msg => lock(&self.writer).send(msg),
+        }
+    }
+
+    /// Performs the closing handshake, sending a close frame and then reading messages until the
+    /// peer answers with its own. Messages received in the meantime are discarded.
+    pub fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        {
+            let mut writer = lock(&self.writer);
+            if !writer.closed {
+                writer.send(Message::Close(frame))?;
+            }
+        }
+        self.keep_alive = None;
+
+        while !self.close_received {
+            self.read()?;
+        }
+
+        Ok(())
+    }
+
+    fn close_received(&mut self, payload: &[u8]) -> Result<Message, WebSocketError> {
+        let frame = CloseFrame::parse(payload).map_err(|err| self.fail(err))?;
+
+        self.close_received = true;
+        self.keep_alive = None;
+
+        let mut writer = lock(&self.writer);
+        if !writer.closed {
+            // Echoes the status code, as the closing handshake asks
+            let echo = frame.as_ref().map(|frame| CloseFrame::new(frame.code, ""));
+            writer.send(Message::Close(echo))?;
+        }
+
+        Ok(Message::Close(frame))
+    }
+
+    /// Closes the connection after the peer misbehaved, with the status code that matches the
+    /// error, or without a close frame when the connection itself failed, and returns the error.
+    fn fail(&mut self, err: WebSocketError) -> WebSocketError {
+        let code = match err {
+            WebSocketError::Protocol(_) => Some(CloseCode::PROTOCOL_ERROR),
+            WebSocketError::InvalidUtf8 => Some(CloseCode::INVALID_PAYLOAD),
+            WebSocketError::MessageTooBig => Some(CloseCode::MESSAGE_TOO_BIG),
+            // The stream may have stopped in the middle of a frame, so nothing can follow
+            _ => None,
+        };
+
+        let mut writer = lock(&self.writer);
+        if let (Some(code), false) = (code, writer.closed) {
+            writer
+                .send(Message::Close(Some(CloseFrame::new(code, ""))))
+                .ok();
+        }
+        writer.closed = true;
+
+        self.close_received = true;
+        self.keep_alive = None;
+        err
+    }
+}
+
+/// Sends messages on a [`WebSocket`] from another thread, obtained with [`WebSocket::sender`].
+#[derive(Debug, Clone)]
+pub struct WebSocketSender {
+    writer: Arc<Mutex<Writer>>,
+}
+
+impl WebSocketSender {
+    /// Sends a message. A [`Message::Close`] starts the closing handshake, which the reading side
+    /// completes once the peer answers.
+    pub fn send(&self, msg: Message) -> Result<(), WebSocketError> {
+        lock(&self.writer).send(msg)
+    }
+}
+
+/// The sending side of a WebSocket, shared between the socket, its senders and its pings.
+#[derive(Debug)]
+struct Writer {
+    conn: Connection,
+    role: Role,
+    frame_size: usize,
+    deflate: bool,
+    closed: bool,
+}
+
+impl Writer {
+    fn send(&mut self, msg: Message) -> Result<(), WebSocketError> {
+        if self.closed {
+            return Err(WebSocketError::ConnectionClosed);
+        }
+
+        match msg {
+            Message::Text(text) => self.write_message(OpCode::Text, text.into_bytes()),
+            Message::Binary(data) => self.write_message(OpCode::Binary, data),
+            Message::Ping(data) => self.write_control(OpCode::Ping, data),
+            Message::Pong(data) => self.write_control(OpCode::Pong, data),
+            Message::Close(frame) => {
+                let payload = frame.map(|frame| frame.payload()).unwrap_or_default();
+                self.write_control(OpCode::Close, payload)?;
+                self.closed = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_message(&mut self, opcode: OpCode, data: Vec<u8>) -> Result<(), WebSocketError> {
+        #[cfg(feature = "websocket-deflate")]
+        let data = match self.deflate {
+            true => deflate::compress(&data)?,
+            false => data,
+        };
+
+        let frame_size = self.frame_size;
+        let frames = data.len().div_ceil(frame_size).max(1);
+
+        for i in 0..frames {
+            let chunk =
+                &data[(i * frame_size).min(data.len())..((i + 1) * frame_size).min(data.len())];
+            self.write_frame(Frame {
+                fin: i == frames - 1,
+                rsv1: i == 0 && self.deflate,
+                opcode: if i == 0 { opcode } else { OpCode::Continuation },
+                payload: chunk.to_vec(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn write_control(&mut self, opcode: OpCode, data: Vec<u8>) -> Result<(), WebSocketError> {
+        if data.len() > 125 {
+            return Err(WebSocketError::Protocol(
+                "control frames are limited to 125 bytes",
+            ));
+        }
+        self.write_frame(Frame::new(opcode, data))
+    }
+
+    fn write_frame(&mut self, frame: Frame) -> Result<(), WebSocketError> {
+        let mask = match self.role {
+            Role::Client => Some((random() as u32).to_ne_bytes()),
+            Role::Server => None,
+        };
+        // A frame written in part leaves the connection unusable
+        frame.write(&mut self.conn, mask).map_err(|err| {
+            self.closed = true;
+            err.into()
+        })
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Random bits, for masking keys and handshake keys.
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::*;
+
+    fn pair() -> (WebSocket, WebSocket) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        (
+            WebSocket::from_connection(client, Role::Client),
+            WebSocket::from_connection(server, Role::Server),
+        )
+    }
+
+    #[test]
+    fn exchanges_fragmented_messages() {
+        let (client, mut server) = pair();
+        let mut client = client.frame_size(4);
+
+        thread::spawn(move || {
+            while let Ok(msg) = server.read() {
+                if let Message::Text(_) | Message::Binary(_) = msg {
+                    server.send(msg).unwrap();
+                }
+            }
+        });
+
+        client.send(Message::text("Hello world")).unwrap();
+        assert_eq!(client.read().unwrap(), Message::text("Hello world"));
+
+        client.send(Message::binary([1, 2, 3, 4, 5])).unwrap();
+        assert_eq!(client.read().unwrap(), Message::binary([1, 2, 3, 4, 5]));
+
+        client.send(Message::Ping(b"ping".to_vec())).unwrap();
+        assert_eq!(client.read().unwrap(), Message::Pong(b"ping".to_vec()));
+    }
+
+    #[test]
+    fn performs_the_closing_handshake() {
+        let (mut client, mut server) = pair();
+
+        let server = thread::spawn(move || {
+            let msg = server.read().unwrap();
+            assert!(matches!(
+                server.read(),
+                Err(WebSocketError::ConnectionClosed)
+            ));
+            msg
+        });
+
+        client
+            .close(Some(CloseFrame::new(CloseCode::GOING_AWAY, "bye")))
+            .unwrap();
+
+        assert_eq!(
+            server.join().unwrap(),
+            Message::Close(Some(CloseFrame::new(CloseCode::GOING_AWAY, "bye")))
+        );
+        assert!(matches!(
+            client.send(Message::text("late")),
+            Err(WebSocketError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn fails_on_invalid_text() {
+        let (mut client, mut server) = pair();
+
+        Frame::new(OpCode::Text, vec![0xff, 0xfe])
+            .write(&mut server.conn, None)
+            .unwrap();
+
+        assert!(matches!(client.read(), Err(WebSocketError::InvalidUtf8)));
+        assert_eq!(
+            server.read().unwrap(),
+            Message::Close(Some(CloseFrame::new(CloseCode::INVALID_PAYLOAD, "")))
+        );
+    }
+
+    #[test]
+    fn limits_message_sizes() {
+        let (client, mut server) = pair();
+        let mut client = client.frame_size(4);
+        server = server.max_message_size(6);
+
+        client.send(Message::text("Hello!")).unwrap();
+        assert_eq!(server.read().unwrap(), Message::text("Hello!"));
+
+        client.send(Message::text("Hello world")).unwrap();
+        assert!(matches!(server.read(), Err(WebSocketError::MessageTooBig)));
+        assert_eq!(
+            client.read().unwrap(),
+            Message::Close(Some(CloseFrame::new(CloseCode::MESSAGE_TOO_BIG, "")))
+        );
+    }
+
+    #[test]
+    fn closes_after_failed_reads() {
+        let (mut client, mut server) = pair();
+        client
+            .get_ref()
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+
+        assert!(matches!(client.read(), Err(WebSocketError::Io(_))));
+
+        server.send(Message::text("late")).unwrap();
+        assert!(matches!(
+            client.read(),
+            Err(WebSocketError::ConnectionClosed)
+        ));
+        assert!(matches!(
+            client.send(Message::text("late")),
+            Err(WebSocketError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_close_codes() {
+        let (mut client, mut server) = pair();
+
+        Frame::new(OpCode::Close, 1005_u16.to_be_bytes().to_vec())
+            .write(&mut server.conn, None)
+            .unwrap();
+
+        assert!(matches!(client.read(), Err(WebSocketError::Protocol(_))));
+        assert_eq!(
+            server.read().unwrap(),
+            Message::Close(Some(CloseFrame::new(CloseCode::PROTOCOL_ERROR, "")))
+        );
+    }
+
+    #[test]
+    fn sends_from_other_threads_and_pings() {
+        let (client, mut server) = pair();
+        let mut client = client.ping_interval(Duration::from_millis(10));
+
+        let sender = server.sender();
+        thread::spawn(move || sender.send(Message::text("Hello")).unwrap())
+            .join()
+            .unwrap();
+
+        assert_eq!(client.read().unwrap(), Message::text("Hello"));
+        assert_eq!(server.read().unwrap(), Message::Ping(Vec::new()));
+        assert_eq!(client.read().unwrap(), Message::Pong(Vec::new()));
+    }
+
+    #[cfg(feature = "websocket-deflate")]
+    #[test]
+    fn compresses_messages() {
+        let (client, server) = pair();
+        let mut client = client.deflate(true).frame_size(16);
+        let mut server = server.deflate(true);
+
+        let text = "Hello world ".repeat(100);
+        client.send(Message::text(&text)).unwrap();
+        assert_eq!(server.read().unwrap(), Message::text(&text));
+
+        server.send(Message::binary(text.as_bytes())).unwrap();
+        assert_eq!(client.read().unwrap(), Message::binary(text.as_bytes()));
+    }
+}
@@ -0,0 +1,248 @@
+use headers::{HeaderMapExt, SecWebsocketAccept, SecWebsocketKey};
+use http::{
+    header::{CONNECTION, SEC_WEBSOCKET_EXTENSIONS, SEC_WEBSOCKET_PROTOCOL, UPGRADE},
+    uri::Scheme,
+    HeaderValue, Method, StatusCode, Uri,
+};
+
+use super::{random, Role, WebSocket, WebSocketError};
+use crate::{
+    client::{ReadAhead, RequestError},
+    Client, Connection,
+};
+
+impl Client {
+    /// Opens a WebSocket to the URI, which may use the `ws`, `wss`, `http` or `https` schemes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use touche::{websocket::Message, Client};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ws = Client::new().websocket("ws://example.com/echo")?;
+    ///
+    /// ws.send(Message::text("Hello world"))?;
+    ///
+    /// while let Message::Text(text) = ws.read()? {
+    ///     println!("{text}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn websocket<U>(&self, uri: U) -> Result<WebSocket, WebSocketError>
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        let req = http::Request::builder()
+            .uri(uri)
+            .body(())
+            .map_err(RequestError::from)?;
+
+        self.websocket_request(req)
+    }
+
+    /// Opens a WebSocket with the opening handshake of
+    /// [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-4.1), sent as the request.
+    ///
+    /// The request can carry headers of its own, such as `Sec-WebSocket-Protocol` to offer
+    /// subprotocols, the one picked by the server being available on [`WebSocket::protocol`].
+    ///
+    /// The client read timeout only applies to the handshake, as WebSockets can stay idle for
+    /// long, and a read that fails leaves them closed.
+    pub fn websocket_request(&self, req: http::Request<()>) -> Result<WebSocket, WebSocketError> {
+        let (mut parts, body) = req.into_parts();
+
+        let mut uri = parts.uri.into_parts();
+        uri.scheme = match uri.scheme {
+            Some(scheme) if scheme.as_str().eq_ignore_ascii_case("ws") => Some(Scheme::HTTP),
+            Some(scheme) if scheme.as_str().eq_ignore_ascii_case("wss") => Some(Scheme::HTTPS),
+            scheme => scheme,
+        };
+        parts.uri = Uri::from_parts(uri).map_err(|_| RequestError::InvalidUri)?;
+        parts.method = Method::GET;
+
+        let key = SecWebsocketKey::from(random_key());
+        let accept = SecWebsocketAccept::from(key.clone());
+
+        let offered = parts.headers.get_all(SEC_WEBSOCKET_PROTOCOL);
+        let offered = offered
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|protocol| protocol.trim().to_owned())
+            .collect::<Vec<_>>();
+
+        parts
+            .headers
+            .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        parts
+            .headers
+            .insert(UPGRADE, HeaderValue::from_static("websocket"));
+        parts
+            .headers
+            .typed_insert(headers::SecWebsocketVersion::V13);
+        parts.headers.typed_insert(key);
+
+        let mut res = self.request(http::Request::from_parts(parts, body))?;
+
+        if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(WebSocketError::Rejected(res.status()));
+        }
+
+        let headers = res.headers();
+
+        if !headers
+            .get(UPGRADE)
+            .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+        {
+            return Err(WebSocketError::Handshake("missing websocket upgrade"));
+        }
+
+        if !headers
+            .typed_get::<headers::Connection>()
+            .is_some_and(|conn| conn.contains(UPGRADE))
+        {
+            return Err(WebSocketError::Handshake("missing connection upgrade"));
+        }
+
+        if headers.typed_get::<SecWebsocketAccept>() != Some(accept) {
+            return Err(WebSocketError::Handshake("invalid accept key"));
+        }
+
+        if headers.contains_key(SEC_WEBSOCKET_EXTENSIONS) {
+            return Err(WebSocketError::Handshake("unrequested extensions"));
+        }
+
+        let protocol = match headers.get(SEC_WEBSOCKET_PROTOCOL) {
+            Some(protocol) => match protocol.to_str() {
+                Ok(protocol) if offered.iter().any(|offered| offered == protocol) => {
+                    Some(protocol.to_owned())
+                }
+                _ => return Err(WebSocketError::Handshake("unrequested subprotocol")),
+            },
+            None => None,
+        };
+
+        let conn = res
+            .extensions_mut()
+            .remove::<Connection>()
+            .ok_or(WebSocketError::Handshake("connection was not upgraded"))?;
+        conn.set_read_timeout(None)?;
+
+        let read_ahead = res
+            .extensions_mut()
+            .remove::<ReadAhead>()
+            .map(|read_ahead| read_ahead.0)
+            .unwrap_or_default();
+
+        Ok(WebSocket {
+            protocol,
+            ..WebSocket::with_read_ahead(conn, Role::Client, read_ahead)
+        })
+    }
+}
+
+fn random_key() -> [u8; 16] {
+    let mut key = [0; 16];
+    key[..8].copy_from_slice(&random().to_ne_bytes());
+    key[8..].copy_from_slice(&random().to_ne_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+    use crate::websocket::Message;
+
+    /// Answers the handshake by hand, sending a first message in the same write as the response.
+    fn serve(listener: TcpListener, protocol: &'static str) {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut headers = http::HeaderMap::new();
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(
+                        http::HeaderName::try_from(name.trim()).unwrap(),
+                        value.trim().parse().unwrap(),
+                    );
+                }
+            }
+
+            let key = headers.typed_get::<SecWebsocketKey>().unwrap();
+            let accept = SecWebsocketAccept::from(key);
+            let mut accept_headers = http::HeaderMap::new();
+            accept_headers.typed_insert(accept);
+
+            let mut res = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\nsec-websocket-protocol: {protocol}\r\nsec-websocket-accept: {}\r\n\r\n",
+                accept_headers["sec-websocket-accept"].to_str().unwrap()
+            )
+            .into_bytes();
+            res.extend_from_slice(&[0x81, 0x05]);
+            res.extend_from_slice(b"Hello");
+
+            let mut stream = stream;
+            stream.write_all(&res).unwrap();
+
+            let mut ws = WebSocket::with_read_ahead(
+                Connection::from(stream),
+                Role::Server,
+                reader.buffer().to_vec(),
+            );
+            while let Ok(msg) = ws.read() {
+                if let Message::Text(_) = msg {
+                    ws.send(msg).unwrap();
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn opens_websockets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        serve(listener, "chat");
+
+        let req = http::Request::builder()
+            .uri(format!("ws://127.0.0.1:{port}/chat"))
+            .header(SEC_WEBSOCKET_PROTOCOL, "superchat, chat")
+            .body(())
+            .unwrap();
+
+        let mut ws = Client::builder()
+            .read_timeout(std::time::Duration::from_secs(1))
+            .build()
+            .websocket_request(req)
+            .unwrap();
+        assert_eq!(ws.protocol(), Some("chat"));
+        assert_eq!(ws.get_ref().read_timeout().unwrap(), None);
+        assert_eq!(ws.read().unwrap(), Message::text("Hello"));
+
+        ws.send(Message::text("Echo")).unwrap();
+        assert_eq!(ws.read().unwrap(), Message::text("Echo"));
+
+        ws.close(None).unwrap();
+    }
+
+    #[test]
+    fn rejects_unrequested_subprotocols() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        serve(listener, "other");
+
+        let res = Client::new().websocket(format!("ws://127.0.0.1:{port}"));
+        assert!(matches!(res, Err(WebSocketError::Handshake(_))));
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::{self, Write};
+
+use flate2::{write::DeflateEncoder, Compression, Decompress, FlushDecompress, Status};
+use http::HeaderValue;
+
+use super::WebSocketError;
+
+/// The end of a sync flush, which senders strip from compressed messages.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Accepts the first `permessage-deflate` offer of a `Sec-WebSocket-Extensions` header that can be
+/// honored, returning the header value to answer with.
+///
+/// Contexts are never taken over between messages, which keeps the memory of idle sockets low and
+/// allows answering any offer that doesn't shrink the window of the server.
+pub(crate) fn negotiate<'a>(
+    offers: impl IntoIterator<Item = &'a HeaderValue>,
+) -> Option<HeaderValue> {
+    let accepted = offers
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|offer| {
+            let mut params = offer.split(';').map(str::trim);
+
+            params.next() == Some("permessage-deflate")
+                && params.all(|param| match param.split_once('=') {
+                    Some(("server_max_window_bits", bits)) => bits.trim_matches('"') == "15",
+                    Some(("client_max_window_bits", _)) => true,
+                    Some(_) => false,
+                    None => matches!(
+                        param,
+                        "server_no_context_takeover"
+                            | "client_no_context_takeover"
+                            | "client_max_window_bits"
+                    ),
+                })
+        });
+
+    accepted.then(|| {
+        HeaderValue::from_static(
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover",
+        )
+    })
+}
+
+/// Compresses a message payload.
+pub(crate) fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)?;
+    encoder.flush()?;
+
+    let mut compressed = std::mem::take(encoder.get_mut());
+    if compressed.ends_with(&TRAILER) {
+        compressed.truncate(compressed.len() - TRAILER.len());
+    }
+    Ok(compressed)
+}
+
+/// Decompresses a message payload, failing once it gets larger than `max_size`.
+pub(crate) fn decompress(data: &[u8], max_size: usize) -> Result<Vec<u8>, WebSocketError> {
+    let input = [data, &TRAILER].concat();
+    let mut inflater = Decompress::new(false);
+    let mut decompressed = Vec::new();
+
+    loop {
+        if decompressed.len() > max_size {
+            return Err(WebSocketError::MessageTooBig);
+        }
+
+        let consumed = inflater.total_in() as usize;
+        let produced = decompressed.len();
+        decompressed.reserve_exact((max_size - produced).saturating_add(1).min(32 * 1024));
+
+        let status = inflater
+            .decompress_vec(&input[consumed..], &mut decompressed, FlushDecompress::Sync)
+            .map_err(|_| WebSocketError::Protocol("invalid compressed message"))?;
+
+        let finished = status == Status::StreamEnd
+            || (inflater.total_in() as usize == input.len()
+                && decompressed.len() < decompressed.capacity());
+
+        if finished {
+            break;
+        } else if inflater.total_in() as usize == consumed && decompressed.len() == produced {
+            return Err(WebSocketError::Protocol("invalid compressed message"));
+        }
+    }
+
+    match decompressed.len() > max_size {
+        true => Err(WebSocketError::MessageTooBig),
+        false => Ok(decompressed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(value: &'static str) -> Option<HeaderValue> {
+        negotiate([&HeaderValue::from_static(value)])
+    }
+
+    #[test]
+    fn negotiates_offers_without_context_takeover() {
+        assert!(offer("permessage-deflate").is_some());
+        assert!(offer("permessage-deflate; client_max_window_bits").is_some());
+        assert!(
+            offer("permessage-deflate; server_max_window_bits=10, permessage-deflate").is_some()
+        );
+        assert!(offer("permessage-deflate; server_max_window_bits=10").is_none());
+        assert!(offer("permessage-deflate; unknown").is_none());
+        assert!(offer("x-webkit-deflate-frame").is_none());
+    }
+
+    #[test]
+    fn compresses_messages() {
+        let data = "Hello world ".repeat(100);
+
+        let compressed = compress(data.as_bytes()).unwrap();
+        assert!(compressed.len() < data.len());
+        assert!(!compressed.ends_with(&TRAILER));
+
+        assert_eq!(decompress(&compressed, 1200).unwrap(), data.as_bytes());
+        assert!(matches!(
+            decompress(&compressed, 1199),
+            Err(WebSocketError::MessageTooBig)
+        ));
+
+        // The example of https://www.rfc-editor.org/rfc/rfc7692#section-7.2.3.1
+        let hello = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        assert_eq!(decompress(&hello, 5).unwrap(), b"Hello");
+    }
+}
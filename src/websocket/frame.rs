@@ -0,0 +1,259 @@
+use std::io::{self, Read, Write};
+
+use super::WebSocketError;
+
+/// The opcodes of [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-5.2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0x0 => Some(OpCode::Continuation),
+            0x1 => Some(OpCode::Text),
+            0x2 => Some(OpCode::Binary),
+            0x8 => Some(OpCode::Close),
+            0x9 => Some(OpCode::Ping),
+            0xa => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xa,
+        }
+    }
+
+    pub(crate) fn is_control(self) -> bool {
+        matches!(self, OpCode::Close | OpCode::Ping | OpCode::Pong)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) fin: bool,
+    pub(crate) rsv1: bool,
+    pub(crate) opcode: OpCode,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Frame {
+    pub(crate) fn new(opcode: OpCode, payload: Vec<u8>) -> Self {
+        Self {
+            fin: true,
+            rsv1: false,
+            opcode,
+            payload,
+        }
+    }
+
+    /// Reads a frame, requiring it to be `masked` or not, as frames sent by clients must be and
+    /// frames sent by servers must not be. Payloads longer than `max_size` are not read.
+    pub(crate) fn read(
+        reader: &mut impl Read,
+        masked: bool,
+        max_size: usize,
+    ) -> Result<Self, WebSocketError> {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+
+        let fin = head[0] & 0x80 != 0;
+        let rsv1 = head[0] & 0x40 != 0;
+        if head[0] & 0x30 != 0 {
+            return Err(WebSocketError::Protocol("reserved bits are set"));
+        }
+
+        let opcode =
+            OpCode::from_bits(head[0] & 0x0f).ok_or(WebSocketError::Protocol("unknown opcode"))?;
+
+        if head[1] & 0x80 != 0 && !masked {
+            return Err(WebSocketError::Protocol(
+                "frames from servers must not be masked",
+            ));
+        } else if head[1] & 0x80 == 0 && masked {
+            return Err(WebSocketError::Protocol(
+                "frames from clients must be masked",
+            ));
+        }
+
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+
+        if opcode.is_control() && (len > 125 || !fin) {
+            return Err(WebSocketError::Protocol(
+                "control frames must be short and unfragmented",
+            ));
+        }
+
+        if len > max_size as u64 {
+            return Err(WebSocketError::MessageTooBig);
+        }
+
+        let mut mask = [0; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+
+        let mut payload = Vec::with_capacity(len.min(64 * 1024) as usize);
+        reader.take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+
+        Ok(Self {
+            fin,
+            rsv1,
+            opcode,
+            payload,
+        })
+    }
+
+    /// Writes the frame, masking its payload with the key when given one.
+    pub(crate) fn write(&self, writer: &mut impl Write, mask: Option<[u8; 4]>) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(self.payload.len() + 14);
+
+        buf.push((self.fin as u8) << 7 | (self.rsv1 as u8) << 6 | self.opcode.bits());
+
+        let mask_bit = (mask.is_some() as u8) << 7;
+        match self.payload.len() {
+            len @ 0..=125 => buf.push(mask_bit | len as u8),
+            len @ 126..=0xffff => {
+                buf.push(mask_bit | 126);
+                buf.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                buf.push(mask_bit | 127);
+                buf.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+
+        let start = buf.len();
+        if let Some(mask) = mask {
+            buf.extend_from_slice(&mask);
+        }
+        buf.extend_from_slice(&self.payload);
+
+        if let Some(mask) = mask {
+            apply_mask(&mut buf[start + 4..], mask);
+        }
+
+        writer.write_all(&buf)?;
+        writer.flush()
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn reads_frames_from_the_rfc() {
+        // https://www.rfc-editor.org/rfc/rfc6455#section-5.7
+        let unmasked = [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f];
+        let frame = Frame::read(&mut Cursor::new(unmasked), false, usize::MAX).unwrap();
+        assert_eq!(frame, Frame::new(OpCode::Text, b"Hello".to_vec()));
+
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = Frame::read(&mut Cursor::new(masked), true, usize::MAX).unwrap();
+        assert_eq!(frame, Frame::new(OpCode::Text, b"Hello".to_vec()));
+
+        let fragment = [0x01, 0x03, 0x48, 0x65, 0x6c];
+        let frame = Frame::read(&mut Cursor::new(fragment), false, usize::MAX).unwrap();
+        assert!(!frame.fin);
+
+        assert!(matches!(
+            Frame::read(&mut Cursor::new(masked), false, usize::MAX),
+            Err(WebSocketError::Protocol(_))
+        ));
+        assert!(matches!(
+            Frame::read(&mut Cursor::new(unmasked), true, usize::MAX),
+            Err(WebSocketError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn writes_frames_of_every_length() {
+        for len in [0, 125, 126, 0xffff, 0x10000] {
+            let frame = Frame::new(OpCode::Binary, vec![7; len]);
+
+            let mut buf = Vec::new();
+            frame.write(&mut buf, Some([1, 2, 3, 4])).unwrap();
+            assert_eq!(
+                Frame::read(&mut Cursor::new(&buf), true, usize::MAX).unwrap(),
+                frame
+            );
+
+            let mut buf = Vec::new();
+            frame.write(&mut buf, None).unwrap();
+            assert_eq!(
+                Frame::read(&mut Cursor::new(&buf), false, usize::MAX).unwrap(),
+                frame
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_frames_over_the_maximum_size() {
+        let mut buf = Vec::new();
+        Frame::new(OpCode::Binary, vec![0; 1024])
+            .write(&mut buf, None)
+            .unwrap();
+
+        assert!(Frame::read(&mut Cursor::new(&buf), false, 1024).is_ok());
+        assert!(matches!(
+            Frame::read(&mut Cursor::new(&buf), false, 1023),
+            Err(WebSocketError::MessageTooBig)
+        ));
+    }
+
+    #[test]
+    fn rejects_long_control_frames() {
+        let mut buf = Vec::new();
+        Frame::new(OpCode::Ping, vec![0; 126])
+            .write(&mut buf, None)
+            .unwrap();
+
+        assert!(matches!(
+            Frame::read(&mut Cursor::new(buf), false, usize::MAX),
+            Err(WebSocketError::Protocol(_))
+        ));
+    }
+}
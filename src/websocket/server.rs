@@ -0,0 +1,298 @@
+use headers::{HeaderMapExt, SecWebsocketAccept, SecWebsocketKey, SecWebsocketVersion};
+#[cfg(feature = "websocket-deflate")]
+use http::header::SEC_WEBSOCKET_EXTENSIONS;
+use http::{
+    header::{CONNECTION, SEC_WEBSOCKET_PROTOCOL, UPGRADE},
+    HeaderValue, Method, StatusCode, Version,
+};
+
+use super::{Role, WebSocket, WebSocketError};
+use crate::{upgrade::Upgrade, Body, Connection};
+
+/// Accepts the WebSocket handshake of a request received by a [`Server`](crate::Server).
+///
+/// The request is validated as asked by
+/// [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-4.2.1), and the `101 Switching
+/// Protocols` response is built with [`Upgrade::upgrade`], handing a [`WebSocket`] to the handler
+/// once it is sent.
+///
+/// # Example
+/// ```no_run
+/// use touche::{
+///     websocket::{Message, WebSocketUpgrade},
+///     Body, Request, Response, Server, StatusCode,
+/// };
+///
+/// fn main() -> std::io::Result<()> {
+///     Server::bind("0.0.0.0:4444").serve(|req: Request<Body>| {
+///         let Ok(upgrade) = WebSocketUpgrade::new(&req) else {
+///             return Response::builder()
+///                 .status(StatusCode::BAD_REQUEST)
+///                 .body(Body::empty());
+///         };
+///
+///         Ok(upgrade.protocols(["chat"]).on_upgrade(|mut ws| {
+///             while let Ok(msg) = ws.read() {
+///                 if let Message::Text(_) = msg {
+///                     if ws.send(msg).is_err() {
+///                         break;
+///                     }
+///                 }
+///             }
+///         }))
+///     })
+/// }
+/// ```
+#[derive(Debug)]
+#[must_use = "handshakes are only accepted by responding with `on_upgrade`"]
+pub struct WebSocketUpgrade {
+    accept: SecWebsocketAccept,
+    offered_protocols: Vec<String>,
+    protocol: Option<String>,
+    #[cfg(feature = "websocket-deflate")]
+    offered_extensions: Vec<HeaderValue>,
+    #[cfg(feature = "websocket-deflate")]
+    deflate: bool,
+}
+
+impl WebSocketUpgrade {
+    /// Validates the opening handshake of the request.
+    pub fn new<B>(req: &http::Request<B>) -> Result<Self, WebSocketError> {
+        let headers = req.headers();
+
+        if req.method() != Method::GET {
+            return Err(WebSocketError::Handshake("method must be GET"));
+        }
+
+        if req.version() < Version::HTTP_11 {
+            return Err(WebSocketError::Handshake(
+                "version must be at least HTTP/1.1",
+            ));
+        }
+
+        let upgrades_to_websocket = headers
+            .get_all(UPGRADE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| protocol.trim().eq_ignore_ascii_case("websocket"));
+
+        if !upgrades_to_websocket {
+            return Err(WebSocketError::Handshake("missing websocket upgrade"));
+        }
+
+        if !headers
+            .typed_get::<headers::Connection>()
+            .is_some_and(|conn| conn.contains(UPGRADE))
+        {
+            return Err(WebSocketError::Handshake("missing connection upgrade"));
+        }
+
+        if headers.typed_get::<SecWebsocketVersion>() != Some(SecWebsocketVersion::V13) {
+            return Err(WebSocketError::Handshake("unsupported version"));
+        }
+
+        let key = headers
+            .typed_get::<SecWebsocketKey>()
+            .ok_or(WebSocketError::Handshake("missing key"))?;
+
+        let offered_protocols = headers
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|protocol| protocol.trim().to_owned())
+            .collect();
+
+        Ok(Self {
+            accept: SecWebsocketAccept::from(key),
+            offered_protocols,
+            protocol: None,
+            #[cfg(feature = "websocket-deflate")]
+            offered_extensions: headers
+                .get_all(SEC_WEBSOCKET_EXTENSIONS)
+                .iter()
+                .cloned()
+                .collect(),
+            #[cfg(feature = "websocket-deflate")]
+            deflate: false,
+        })
+    }
+
+    /// Picks the first of the supported subprotocols that the client offered, if any.
+    pub fn protocols<P: AsRef<str>>(self, supported: impl IntoIterator<Item = P>) -> Self {
+        let protocol = supported
+            .into_iter()
+            .find(|protocol| {
+                self.offered_protocols
+                    .iter()
+                    .any(|offered| offered == protocol.as_ref())
+            })
+            .map(|protocol| protocol.as_ref().to_owned());
+
+        Self { protocol, ..self }
+    }
+
+    /// Compresses the messages with the `permessage-deflate` extension of
+    /// [RFC 7692](https://www.rfc-editor.org/rfc/rfc7692), when the client offers it.
+    #[cfg(feature = "websocket-deflate")]
+    pub fn deflate(self, enabled: bool) -> Self {
+        Self {
+            deflate: enabled,
+            ..self
+        }
+    }
+
+    /// Builds the `101 Switching Protocols` response, which calls the handler with the socket once
+    /// it is sent.
+    pub fn on_upgrade<F>(self, handler: F) -> http::Response<Body>
+    where
+        F: Fn(WebSocket) + Send + Sync + 'static,
+    {
+        let mut res = http::Response::new(Body::empty());
+        *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+
+        let headers = res.headers_mut();
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.typed_insert(self.accept);
+
+        if let Some(protocol) = self
+            .protocol
+            .as_deref()
+            .and_then(|protocol| HeaderValue::from_str(protocol).ok())
+        {
+            headers.insert(SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+
+        #[cfg(feature = "websocket-deflate")]
+        let deflate = match self.deflate {
+            true => super::deflate::negotiate(&self.offered_extensions),
+            false => None,
+        };
+
+        #[cfg(feature = "websocket-deflate")]
+        if let Some(extension) = &deflate {
+            headers.insert(SEC_WEBSOCKET_EXTENSIONS, extension.clone());
+        }
+
+        let protocol = self.protocol;
+
+        res.upgrade(move |conn: Connection| {
+            let ws = WebSocket {
+                protocol: protocol.clone(),
+                ..WebSocket::from_connection(conn, Role::Server)
+            };
+
+            #[cfg(feature = "websocket-deflate")]
+            let ws = ws.deflate(deflate.is_some());
+
+            handler(ws)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake() -> http::request::Builder {
+        http::Request::builder()
+            .header("upgrade", "websocket")
+            .header("connection", "keep-alive, Upgrade")
+            .header("sec-websocket-version", "13")
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+    }
+
+    #[test]
+    fn validates_handshakes() {
+        let req = handshake()
+            .header("sec-websocket-protocol", "superchat, chat")
+            .body(())
+            .unwrap();
+
+        let res = WebSocketUpgrade::new(&req)
+            .unwrap()
+            .protocols(["v2.chat", "chat"])
+            .on_upgrade(|_ws| {});
+
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(res.headers()["upgrade"], "websocket");
+        assert_eq!(
+            res.headers()["sec-websocket-accept"],
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(res.headers()["sec-websocket-protocol"], "chat");
+
+        let req = handshake().method("POST").body(()).unwrap();
+        assert!(WebSocketUpgrade::new(&req).is_err());
+
+        let mut req = handshake().body(()).unwrap();
+        req.headers_mut()
+            .insert("sec-websocket-version", HeaderValue::from_static("8"));
+        assert!(WebSocketUpgrade::new(&req).is_err());
+
+        let req = http::Request::builder()
+            .header("upgrade", "websocket")
+            .body(())
+            .unwrap();
+        assert!(WebSocketUpgrade::new(&req).is_err());
+    }
+
+    #[cfg(feature = "websocket-deflate")]
+    #[test]
+    fn negotiates_compression() {
+        let req = handshake()
+            .header("sec-websocket-extensions", "permessage-deflate")
+            .body(())
+            .unwrap();
+
+        let res = WebSocketUpgrade::new(&req).unwrap().on_upgrade(|_ws| {});
+        assert!(!res.headers().contains_key("sec-websocket-extensions"));
+
+        let res = WebSocketUpgrade::new(&req)
+            .unwrap()
+            .deflate(true)
+            .on_upgrade(|_ws| {});
+        assert_eq!(
+            res.headers()["sec-websocket-extensions"],
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover"
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn serves_websockets() {
+        use std::{net::TcpListener, thread};
+
+        use crate::{websocket::Message, Client, Server};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            Server::builder()
+                .max_threads(16)
+                .from_listener(listener)
+                .unwrap()
+                .serve(|req: http::Request<Body>| {
+                    Ok::<_, WebSocketError>(WebSocketUpgrade::new(&req)?.on_upgrade(|mut ws| {
+                        while let Ok(msg) = ws.read() {
+                            if let Message::Text(text) = msg {
+                                ws.send(Message::text(text.to_uppercase())).unwrap();
+                            }
+                        }
+                    }))
+                })
+                .ok()
+        });
+
+        let mut ws = Client::new()
+            .websocket(format!("ws://127.0.0.1:{port}"))
+            .unwrap();
+
+        ws.send(Message::text("hello")).unwrap();
+        assert_eq!(ws.read().unwrap(), Message::text("HELLO"));
+        ws.close(None).unwrap();
+    }
+}